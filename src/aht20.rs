@@ -0,0 +1,186 @@
+use log::*;
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+use crate::sensor::{crc8, EnvironmentReading, EnvironmentSensor, SensorError};
+
+/// I2C address of the AHT20 sensor
+pub const AHT20_ADDRESS: u8 = 0x38;
+
+/// Command for Initialize, loads the calibration coefficients
+const INITIALIZE: [u8; 3] = [0xbe, 0x08, 0x00];
+
+/// Command for Trigger Measurement
+const TRIGGER_MEASUREMENT: [u8; 3] = [0xac, 0x33, 0x00];
+
+/// Status bit set while a measurement is in progress
+const STATUS_BUSY: u8 = 0x80;
+
+/// Status bit set when the calibration coefficients are loaded
+const STATUS_CALIBRATED: u8 = 0x08;
+
+/// Time the sensor needs after power on in milliseconds
+const POWER_ON_TIME_MS: u32 = 40;
+
+/// Time the initialize command takes in milliseconds
+const INITIALIZE_TIME_MS: u32 = 10;
+
+/// Time a measurement takes in milliseconds
+const MEASUREMENT_TIME_MS: u32 = 80;
+
+/// AHT20 async temperature and humidity sensor driver.
+///
+/// # Type Parameters
+///
+/// - `I2C`: The I2C bus the sensor is connected to.
+/// - `D`: The delay used to wait for the measurement to finish.
+///
+/// # Constraints
+///
+/// - `I2C`: Must implement the async `I2c` trait.
+/// - `D`: Must implement the async `DelayNs` trait.
+pub struct Aht20<I2C, D>
+where
+    I2C: I2c,
+    D: DelayNs,
+{
+    /// I2C bus used for communication with the sensor.
+    i2c: I2C,
+    /// Delay used to wait for the measurement to finish.
+    delay: D,
+    /// I2C address of the sensor.
+    address: u8,
+}
+
+impl<I2C, D> Aht20<I2C, D>
+where
+    I2C: I2c,
+    D: DelayNs,
+{
+    /// Creates a new driver instance using the default AHT20 address.
+    pub fn new(i2c: I2C, delay: D) -> Self {
+        Self {
+            i2c,
+            delay,
+            address: AHT20_ADDRESS,
+        }
+    }
+
+    /// Release the I2C bus used by the driver
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+}
+
+impl<I2C, D> EnvironmentSensor for Aht20<I2C, D>
+where
+    I2C: I2c,
+    D: DelayNs,
+{
+    fn name(&self) -> &'static str {
+        "AHT20"
+    }
+
+    async fn init(&mut self) -> Result<(), SensorError> {
+        self.delay.delay_ms(POWER_ON_TIME_MS).await;
+
+        let mut status = [0u8; 1];
+        self.i2c.read(self.address, &mut status).await?;
+
+        if (status[0] & STATUS_CALIBRATED) == 0 {
+            debug!("AHT20 initialize");
+            self.i2c.write(self.address, &INITIALIZE).await?;
+            self.delay.delay_ms(INITIALIZE_TIME_MS).await;
+        }
+
+        Ok(())
+    }
+
+    async fn read(&mut self) -> Result<EnvironmentReading, SensorError> {
+        self.i2c.write(self.address, &TRIGGER_MEASUREMENT).await?;
+        self.delay.delay_ms(MEASUREMENT_TIME_MS).await;
+
+        // Status, 20 bits humidity, 20 bits temperature, CRC
+        let mut data = [0u8; 7];
+        self.i2c.read(self.address, &mut data).await?;
+
+        if (data[0] & STATUS_BUSY) != 0 {
+            return Err(SensorError::Busy);
+        }
+
+        if crc8(&data[0..6]) != data[6] {
+            return Err(SensorError::Checksum);
+        }
+
+        let raw_humidity =
+            ((data[1] as u32) << 12) | ((data[2] as u32) << 4) | ((data[3] as u32) >> 4);
+        let raw_temperature =
+            (((data[3] & 0x0f) as u32) << 16) | ((data[4] as u32) << 8) | data[5] as u32;
+
+        // RH = 100 * raw / 2^20 and T = 200 * raw / 2^20 - 50, both in tenths
+        let humidity_tenths = ((1000 * raw_humidity as u64) >> 20) as u16;
+        let temp_tenths_celsius = (((2000 * raw_temperature as u64) >> 20) as i32 - 500) as i16;
+
+        Ok(EnvironmentReading {
+            temp_tenths_celsius,
+            humidity_tenths: Some(humidity_tenths),
+            pressure_pa: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::test_util::{block_on, I2cResponse, MockI2c, Recorder};
+
+    /// Measurement with the status, the data and its CRC
+    fn measurement(status: u8, data: [u8; 5]) -> Vec<I2cResponse> {
+        let mut frame = [&[status][..], &data[..]].concat();
+        frame.push(crc8(&frame));
+        vec![I2cResponse::Read(vec![]), I2cResponse::Read(frame)]
+    }
+
+    #[test]
+    fn read_unpacks_the_20_bit_values() {
+        // Humidity 0x12345 and temperature 0x6789a share the nibbles of the third byte
+        let responses = measurement(0x1c, [0x12, 0x34, 0x56, 0x78, 0x9a]);
+        let mut aht20 = Aht20::new(MockI2c::new(responses), Recorder::default().delay());
+
+        assert_eq!(
+            block_on(aht20.read()),
+            Ok(EnvironmentReading {
+                temp_tenths_celsius: 308,
+                humidity_tenths: Some(71),
+                pressure_pa: None,
+            })
+        );
+        assert_eq!(
+            aht20.release().writes,
+            [TRIGGER_MEASUREMENT.to_vec(), vec![]]
+        );
+    }
+
+    #[test]
+    fn read_rejects_a_busy_sensor() {
+        let responses = measurement(0x1c | STATUS_BUSY, [0x12, 0x34, 0x56, 0x78, 0x9a]);
+        let mut aht20 = Aht20::new(MockI2c::new(responses), Recorder::default().delay());
+
+        assert_eq!(block_on(aht20.read()), Err(SensorError::Busy));
+    }
+
+    #[test]
+    fn read_rejects_a_bad_crc() {
+        let mut responses = measurement(0x1c, [0x12, 0x34, 0x56, 0x78, 0x9a]);
+        if let I2cResponse::Read(frame) = &mut responses[1] {
+            frame[6] ^= 0x01;
+        }
+        let mut aht20 = Aht20::new(MockI2c::new(responses), Recorder::default().delay());
+
+        assert_eq!(block_on(aht20.read()), Err(SensorError::Checksum));
+    }
+}
@@ -0,0 +1,357 @@
+use heapless::Vec;
+
+use crate::sensor::EnvironmentReading;
+
+/// Number of alarms watched by the monitor, a low and high alarm per quantity
+const ALARM_COUNT: usize = 4;
+
+/// Quantity of a reading an alarm watches
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Quantity {
+    Temperature,
+    Humidity,
+}
+
+/// Side of the threshold the value has to cross to raise an alarm
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Limit {
+    Low,
+    High,
+}
+
+/// Identifies one of the alarms
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Alarm {
+    pub quantity: Quantity,
+    pub limit: Limit,
+}
+
+/// Published when an alarm changes state, for the tasks that act on alarms
+///
+/// The value is the reading that changed the state, in tenths of a degree celsius
+/// or tenths of a percent.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AlarmEvent {
+    /// The value stayed past the threshold for the minimum duration
+    Raised {
+        alarm: Alarm,
+        value: i16,
+        at_secs: u64,
+    },
+
+    /// The value stayed back within the hysteresis for the minimum duration
+    Cleared {
+        alarm: Alarm,
+        value: i16,
+        at_secs: u64,
+    },
+}
+
+impl AlarmEvent {
+    /// Alarm that changed state
+    pub fn alarm(&self) -> Alarm {
+        match self {
+            AlarmEvent::Raised { alarm, .. } | AlarmEvent::Cleared { alarm, .. } => *alarm,
+        }
+    }
+}
+
+/// Alarm thresholds of one quantity
+///
+/// The values use the same fixed point unit (tenths) as the reading, tenths of a
+/// degree celsius for the temperature and tenths of a percent for the humidity.
+#[derive(Clone, Copy, Debug)]
+pub struct ThresholdConfig {
+    /// Raise an alarm below this value, `None` disables the low alarm
+    pub low: Option<i16>,
+    /// Raise an alarm above this value, `None` disables the high alarm
+    pub high: Option<i16>,
+    /// Distance the value has to move back past the threshold to clear the alarm
+    pub hysteresis: i16,
+    /// Time the condition has to hold before the alarm is raised or cleared
+    pub min_duration_secs: u64,
+}
+
+/// Alarm thresholds of all quantities
+#[derive(Clone, Copy, Debug)]
+pub struct AlarmConfig {
+    pub temperature: ThresholdConfig,
+    pub humidity: ThresholdConfig,
+}
+
+/// Debounced state of a single alarm
+#[derive(Clone, Copy, Debug)]
+struct AlarmState {
+    alarm: Alarm,
+    threshold: Option<i16>,
+    hysteresis: i16,
+    min_duration_secs: u64,
+    active: bool,
+    /// Time the value first met the condition to change the state
+    pending_since: Option<u64>,
+}
+
+impl AlarmState {
+    const fn new(quantity: Quantity, limit: Limit, config: &ThresholdConfig) -> Self {
+        Self {
+            alarm: Alarm { quantity, limit },
+            threshold: match limit {
+                Limit::Low => config.low,
+                Limit::High => config.high,
+            },
+            hysteresis: config.hysteresis,
+            min_duration_secs: config.min_duration_secs,
+            active: false,
+            pending_since: None,
+        }
+    }
+
+    /// Update the state with a value measured at `now_secs`, returns an event if
+    /// the state changed.
+    fn update(&mut self, now_secs: u64, value: i16) -> Option<AlarmEvent> {
+        let threshold = self.threshold?;
+
+        let crossed = match (self.alarm.limit, self.active) {
+            (Limit::Low, false) => value < threshold,
+            (Limit::High, false) => value > threshold,
+            (Limit::Low, true) => value >= threshold.saturating_add(self.hysteresis),
+            (Limit::High, true) => value <= threshold.saturating_sub(self.hysteresis),
+        };
+
+        if !crossed {
+            self.pending_since = None;
+            return None;
+        }
+
+        // The condition has to hold on every reading for the minimum duration
+        let since = *self.pending_since.get_or_insert(now_secs);
+        if now_secs.saturating_sub(since) < self.min_duration_secs {
+            return None;
+        }
+
+        self.pending_since = None;
+        self.active = !self.active;
+
+        let alarm = self.alarm;
+        Some(if self.active {
+            AlarmEvent::Raised {
+                alarm,
+                value,
+                at_secs: now_secs,
+            }
+        } else {
+            AlarmEvent::Cleared {
+                alarm,
+                value,
+                at_secs: now_secs,
+            }
+        })
+    }
+}
+
+/// Watches the readings for values outside the configured thresholds.
+///
+/// An alarm is raised when the value stays past its threshold for the minimum
+/// duration, and cleared when it stays back within the threshold minus the
+/// hysteresis for the minimum duration. This keeps a value hovering around a
+/// threshold from toggling the alarm on every reading.
+pub struct AlarmMonitor {
+    alarms: [AlarmState; ALARM_COUNT],
+}
+
+impl AlarmMonitor {
+    /// Creates a monitor with all alarms cleared.
+    pub const fn new(config: AlarmConfig) -> Self {
+        Self {
+            alarms: [
+                AlarmState::new(Quantity::Temperature, Limit::Low, &config.temperature),
+                AlarmState::new(Quantity::Temperature, Limit::High, &config.temperature),
+                AlarmState::new(Quantity::Humidity, Limit::Low, &config.humidity),
+                AlarmState::new(Quantity::Humidity, Limit::High, &config.humidity),
+            ],
+        }
+    }
+
+    /// Evaluate a reading taken at `now_secs`, returns the alarms that changed state.
+    ///
+    /// Humidity alarms keep their state for readings without a humidity.
+    pub fn update(
+        &mut self,
+        now_secs: u64,
+        reading: &EnvironmentReading,
+    ) -> Vec<AlarmEvent, ALARM_COUNT> {
+        let mut events = Vec::new();
+
+        for state in self.alarms.iter_mut() {
+            let value = match state.alarm.quantity {
+                Quantity::Temperature => Some(reading.temp_tenths_celsius),
+                Quantity::Humidity => reading.humidity_tenths.map(|h| h as i16),
+            };
+
+            if let Some(event) = value.and_then(|value| state.update(now_secs, value)) {
+                let _ = events.push(event);
+            }
+        }
+
+        events
+    }
+
+    /// Whether the low or high alarm of the quantity is raised
+    pub fn is_active(&self, quantity: Quantity) -> bool {
+        self.alarms
+            .iter()
+            .any(|state| state.alarm.quantity == quantity && state.active)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: AlarmConfig = AlarmConfig {
+        temperature: ThresholdConfig {
+            low: Some(100),
+            high: Some(300),
+            hysteresis: 10,
+            min_duration_secs: 10,
+        },
+        humidity: ThresholdConfig {
+            low: None,
+            high: Some(700),
+            hysteresis: 50,
+            min_duration_secs: 0,
+        },
+    };
+
+    const TEMPERATURE_HIGH: Alarm = Alarm {
+        quantity: Quantity::Temperature,
+        limit: Limit::High,
+    };
+
+    const TEMPERATURE_LOW: Alarm = Alarm {
+        quantity: Quantity::Temperature,
+        limit: Limit::Low,
+    };
+
+    const HUMIDITY_HIGH: Alarm = Alarm {
+        quantity: Quantity::Humidity,
+        limit: Limit::High,
+    };
+
+    fn reading(temp_tenths_celsius: i16, humidity_tenths: Option<u16>) -> EnvironmentReading {
+        EnvironmentReading {
+            temp_tenths_celsius,
+            humidity_tenths,
+            pressure_pa: None,
+        }
+    }
+
+    /// Events of temperature readings at the given times, the humidity is normal
+    fn update(monitor: &mut AlarmMonitor, readings: &[(u64, i16)]) -> Vec<AlarmEvent, 16> {
+        let mut events = Vec::new();
+        for (now_secs, temperature) in readings {
+            for event in monitor.update(*now_secs, &reading(*temperature, Some(500))) {
+                let _ = events.push(event);
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn a_short_crossing_does_not_raise_the_alarm() {
+        let mut monitor = AlarmMonitor::new(CONFIG);
+
+        // Back below the threshold before the minimum duration, the next crossing
+        // starts the duration again
+        assert_eq!(
+            update(
+                &mut monitor,
+                &[(0, 305), (5, 310), (8, 300), (12, 305), (20, 305)]
+            ),
+            []
+        );
+        assert!(!monitor.is_active(Quantity::Temperature));
+
+        assert_eq!(
+            update(&mut monitor, &[(22, 306)]),
+            [AlarmEvent::Raised {
+                alarm: TEMPERATURE_HIGH,
+                value: 306,
+                at_secs: 22
+            }]
+        );
+        assert!(monitor.is_active(Quantity::Temperature));
+    }
+
+    #[test]
+    fn high_alarm_clears_only_back_past_the_hysteresis() {
+        let mut monitor = AlarmMonitor::new(CONFIG);
+        assert_eq!(update(&mut monitor, &[(0, 320), (10, 320)]).len(), 1);
+
+        // Below the threshold but within the hysteresis
+        assert_eq!(update(&mut monitor, &[(20, 299), (40, 291), (60, 295)]), []);
+        assert!(monitor.is_active(Quantity::Temperature));
+
+        assert_eq!(
+            update(&mut monitor, &[(70, 290), (75, 280), (80, 285)]),
+            [AlarmEvent::Cleared {
+                alarm: TEMPERATURE_HIGH,
+                value: 285,
+                at_secs: 80
+            }]
+        );
+        assert!(!monitor.is_active(Quantity::Temperature));
+    }
+
+    #[test]
+    fn low_alarm_clears_only_back_past_the_hysteresis() {
+        let mut monitor = AlarmMonitor::new(CONFIG);
+        assert_eq!(
+            update(&mut monitor, &[(0, 95), (10, 90)]),
+            [AlarmEvent::Raised {
+                alarm: TEMPERATURE_LOW,
+                value: 90,
+                at_secs: 10
+            }]
+        );
+
+        assert_eq!(update(&mut monitor, &[(20, 101), (40, 109)]), []);
+
+        assert_eq!(
+            update(&mut monitor, &[(50, 110), (60, 115)]),
+            [AlarmEvent::Cleared {
+                alarm: TEMPERATURE_LOW,
+                value: 115,
+                at_secs: 60
+            }]
+        );
+    }
+
+    #[test]
+    fn humidity_alarm_keeps_its_state_without_humidity() {
+        let mut monitor = AlarmMonitor::new(CONFIG);
+
+        let raised = AlarmEvent::Raised {
+            alarm: HUMIDITY_HIGH,
+            value: 750,
+            at_secs: 0,
+        };
+        assert_eq!(monitor.update(0, &reading(200, Some(750))), [raised]);
+        assert_eq!(raised.alarm(), HUMIDITY_HIGH);
+
+        assert_eq!(monitor.update(2, &reading(200, None)), []);
+        assert!(monitor.is_active(Quantity::Humidity));
+        assert!(!monitor.is_active(Quantity::Temperature));
+
+        // Within the hysteresis, then back past it
+        assert_eq!(monitor.update(4, &reading(200, Some(660))), []);
+        assert_eq!(
+            monitor.update(6, &reading(200, Some(650))),
+            [AlarmEvent::Cleared {
+                alarm: HUMIDITY_HIGH,
+                value: 650,
+                at_secs: 6
+            }]
+        );
+    }
+}
@@ -0,0 +1,296 @@
+use embedded_hal::digital::OutputPin;
+use embedded_hal::pwm::SetDutyCycle;
+use embedded_hal_async::delay::DelayNs;
+
+use crate::ili9341_async::Error;
+
+/// Brightness of a fully lit backlight in percent
+pub const MAX_BRIGHTNESS: u8 = 100;
+
+/// Time between two brightness steps of a fade in milliseconds
+const FADE_STEP_MS: u32 = 10;
+
+/// Backlight of the display with a brightness in percent.
+///
+/// Use [`PwmBacklight`] for a backlight driven by a PWM channel, e.g. the LEDC of
+/// the ESP32, and [`PinBacklight`] for a backlight that can only be switched on or off.
+pub trait Backlight {
+    /// Set the brightness, `0` turns the backlight off and values above 100 are full brightness
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if setting the pin or PWM channel fails.
+    fn set_brightness(&mut self, percent: u8) -> Result<(), Error>;
+
+    /// Current brightness in percent
+    fn brightness(&self) -> u8;
+}
+
+/// Backlight switched by a GPIO pin, any brightness above 0 turns it on.
+pub struct PinBacklight<P> {
+    /// Pin powering the backlight when high.
+    pin: P,
+    /// Whether the pin is high.
+    on: bool,
+}
+
+impl<P> PinBacklight<P>
+where
+    P: OutputPin,
+{
+    /// Creates a backlight switched by `pin`, the backlight is assumed to be off
+    pub fn new(pin: P) -> Self {
+        Self { pin, on: false }
+    }
+}
+
+impl<P> Backlight for PinBacklight<P>
+where
+    P: OutputPin,
+{
+    fn set_brightness(&mut self, percent: u8) -> Result<(), Error> {
+        let on = percent > 0;
+        if on {
+            self.pin.set_high().map_err(Error::from_digital)?;
+        } else {
+            self.pin.set_low().map_err(Error::from_digital)?;
+        }
+        self.on = on;
+
+        Ok(())
+    }
+
+    fn brightness(&self) -> u8 {
+        if self.on {
+            MAX_BRIGHTNESS
+        } else {
+            0
+        }
+    }
+}
+
+/// Backlight dimmed by the duty cycle of a PWM channel.
+pub struct PwmBacklight<P> {
+    /// PWM channel driving the backlight.
+    pwm: P,
+    /// Current brightness in percent.
+    brightness: u8,
+}
+
+impl<P> PwmBacklight<P>
+where
+    P: SetDutyCycle,
+{
+    /// Creates a backlight driven by `pwm`, the backlight is assumed to be off
+    pub fn new(pwm: P) -> Self {
+        Self { pwm, brightness: 0 }
+    }
+}
+
+impl<P> Backlight for PwmBacklight<P>
+where
+    P: SetDutyCycle,
+{
+    fn set_brightness(&mut self, percent: u8) -> Result<(), Error> {
+        let percent = percent.min(MAX_BRIGHTNESS);
+        self.pwm
+            .set_duty_cycle_percent(percent)
+            .map_err(Error::from_pwm)?;
+        self.brightness = percent;
+
+        Ok(())
+    }
+
+    fn brightness(&self) -> u8 {
+        self.brightness
+    }
+}
+
+/// Change the brightness gradually from the current brightness to `percent`.
+///
+/// # Parameters
+///
+/// - `backlight`: The backlight to fade.
+/// - `percent`: Brightness at the end of the fade.
+/// - `duration_ms`: Duration of the fade in milliseconds.
+/// - `delay`: Waits between the brightness steps.
+///
+/// # Errors
+///
+/// Returns an error if setting the brightness fails.
+pub async fn fade<B, D>(
+    backlight: &mut B,
+    percent: u8,
+    duration_ms: u32,
+    delay: &mut D,
+) -> Result<(), Error>
+where
+    B: Backlight,
+    D: DelayNs,
+{
+    let start = backlight.brightness() as i32;
+    let target = percent.min(MAX_BRIGHTNESS) as i32;
+    let steps = (duration_ms / FADE_STEP_MS).max(1) as i32;
+
+    for step in 1..=steps {
+        let brightness = start + (target - start) * step / steps;
+        backlight.set_brightness(brightness as u8)?;
+
+        if step < steps {
+            delay.delay_ms(FADE_STEP_MS).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Dims the backlight after a period without activity.
+///
+/// The policy only decides the brightness, the caller reports activity, e.g. a
+/// button press or a raised alarm, polls the policy and applies the brightness it
+/// returns, e.g. with [`fade`].
+pub struct AutoDim {
+    /// Time without activity before the backlight is dimmed.
+    timeout_ms: u64,
+    /// Brightness while there is activity.
+    active_brightness: u8,
+    /// Brightness after the timeout, 0 turns the backlight off.
+    dimmed_brightness: u8,
+    /// Time of the last activity.
+    last_activity_ms: u64,
+    /// Whether the backlight is dimmed.
+    dimmed: bool,
+}
+
+impl AutoDim {
+    /// Creates a policy dimming from `active_brightness` to `dimmed_brightness` after
+    /// `timeout_ms` without activity, counting from time 0.
+    pub const fn new(timeout_ms: u64, active_brightness: u8, dimmed_brightness: u8) -> Self {
+        Self {
+            timeout_ms,
+            active_brightness,
+            dimmed_brightness,
+            last_activity_ms: 0,
+            dimmed: false,
+        }
+    }
+
+    /// Report activity at `now_ms`, returns the brightness to restore if the backlight was dimmed
+    pub fn activity(&mut self, now_ms: u64) -> Option<u8> {
+        self.last_activity_ms = now_ms;
+
+        if self.dimmed {
+            self.dimmed = false;
+            Some(self.active_brightness)
+        } else {
+            None
+        }
+    }
+
+    /// Check the timeout at `now_ms`, returns the brightness to dim to once it passed
+    pub fn poll(&mut self, now_ms: u64) -> Option<u8> {
+        if !self.dimmed && now_ms.saturating_sub(self.last_activity_ms) >= self.timeout_ms {
+            self.dimmed = true;
+            Some(self.dimmed_brightness)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the backlight is dimmed
+    pub fn is_dimmed(&self) -> bool {
+        self.dimmed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::test_util::{block_on, Event, MockPwm, Recorder};
+
+    /// Duty cycles of the brightness in percent
+    fn duty_cycles(percents: &[u32]) -> Vec<Event> {
+        percents
+            .iter()
+            .map(|percent| {
+                Event::DutyCycle((percent * MockPwm::MAX_DUTY_CYCLE as u32 / 100) as u16)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pwm_backlight_sets_the_duty_cycle_of_the_brightness() {
+        let recorder = Recorder::default();
+        let mut backlight = PwmBacklight::new(recorder.pwm());
+
+        backlight.set_brightness(40).unwrap();
+        assert_eq!(backlight.brightness(), 40);
+        backlight.set_brightness(150).unwrap();
+        assert_eq!(backlight.brightness(), MAX_BRIGHTNESS);
+
+        assert_eq!(recorder.take(), duty_cycles(&[40, 100]));
+    }
+
+    #[test]
+    fn fade_steps_from_the_current_brightness() {
+        let recorder = Recorder::default();
+        let mut backlight = PwmBacklight::new(recorder.pwm());
+        let mut delay = recorder.delay();
+        let step = Event::Delay { ms: FADE_STEP_MS };
+
+        block_on(fade(&mut backlight, 100, 4 * FADE_STEP_MS, &mut delay)).unwrap();
+        let expected = [
+            duty_cycles(&[25]),
+            vec![step.clone()],
+            duty_cycles(&[50]),
+            vec![step.clone()],
+            duty_cycles(&[75]),
+            vec![step.clone()],
+            duty_cycles(&[100]),
+        ]
+        .concat();
+        assert_eq!(recorder.take(), expected);
+
+        // Every step is rounded towards the start
+        block_on(fade(&mut backlight, 0, 3 * FADE_STEP_MS, &mut delay)).unwrap();
+        let expected = [
+            duty_cycles(&[67]),
+            vec![step.clone()],
+            duty_cycles(&[34]),
+            vec![step],
+            duty_cycles(&[0]),
+        ]
+        .concat();
+        assert_eq!(recorder.take(), expected);
+    }
+
+    #[test]
+    fn fade_shorter_than_a_step_sets_the_brightness_at_once() {
+        let recorder = Recorder::default();
+        let mut backlight = PwmBacklight::new(recorder.pwm());
+
+        block_on(fade(&mut backlight, 60, 0, &mut recorder.delay())).unwrap();
+
+        assert_eq!(recorder.take(), duty_cycles(&[60]));
+    }
+
+    #[test]
+    fn auto_dim_dims_after_the_timeout_and_restores_on_activity() {
+        let mut auto_dim = AutoDim::new(1000, 100, 20);
+
+        assert_eq!(auto_dim.poll(999), None);
+        assert_eq!(auto_dim.poll(1000), Some(20));
+        assert!(auto_dim.is_dimmed());
+        assert_eq!(auto_dim.poll(5000), None);
+
+        assert_eq!(auto_dim.activity(5500), Some(100));
+        assert!(!auto_dim.is_dimmed());
+        assert_eq!(auto_dim.activity(5600), None);
+
+        // The timeout counts from the last activity
+        assert_eq!(auto_dim.poll(6599), None);
+        assert_eq!(auto_dim.poll(6600), Some(20));
+    }
+}
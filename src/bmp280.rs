@@ -0,0 +1,390 @@
+use log::*;
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+use crate::sensor::{EnvironmentReading, EnvironmentSensor, SensorError};
+
+/// I2C address of the BMP280 / BME280 sensor with the SDO pin low
+pub const BMP280_ADDRESS: u8 = 0x76;
+
+/// Register for the Chip ID
+const REG_CHIP_ID: u8 = 0xd0;
+
+/// Register for the first temperature and pressure calibration value
+const REG_CALIBRATION: u8 = 0x88;
+
+/// Register for the first humidity calibration value (BME280 only)
+const REG_CALIBRATION_H1: u8 = 0xa1;
+
+/// Register for the remaining humidity calibration values (BME280 only)
+const REG_CALIBRATION_H2: u8 = 0xe1;
+
+/// Register for Humidity Control (BME280 only)
+const REG_CTRL_HUM: u8 = 0xf2;
+
+/// Register for Measurement Control
+const REG_CTRL_MEAS: u8 = 0xf4;
+
+/// Register for the first measurement data byte
+const REG_DATA: u8 = 0xf7;
+
+/// Chip ID of the BMP280
+const CHIP_ID_BMP280: u8 = 0x58;
+
+/// Chip ID of the BME280
+const CHIP_ID_BME280: u8 = 0x60;
+
+/// Oversampling x1 for humidity
+const CTRL_HUM_OVERSAMPLING_X1: u8 = 0x01;
+
+/// Temperature and pressure oversampling x1 in forced mode
+const CTRL_MEAS_FORCED_X1: u8 = (0b001 << 5) | (0b001 << 2) | 0b01;
+
+/// Time a forced measurement with oversampling x1 takes in milliseconds
+const MEASUREMENT_TIME_MS: u32 = 10;
+
+/// Factory calibration values stored in the sensor
+#[derive(Clone, Copy, Debug, Default)]
+struct Calibration {
+    t1: u16,
+    t2: i16,
+    t3: i16,
+    p1: u16,
+    p2: i16,
+    p3: i16,
+    p4: i16,
+    p5: i16,
+    p6: i16,
+    p7: i16,
+    p8: i16,
+    p9: i16,
+    h1: u8,
+    h2: i16,
+    h3: u8,
+    h4: i16,
+    h5: i16,
+    h6: i8,
+}
+
+/// BMP280 and BME280 async temperature, pressure and humidity sensor driver.
+///
+/// The chip ID is read during `init`, the humidity is only reported by the BME280.
+///
+/// # Type Parameters
+///
+/// - `I2C`: The I2C bus the sensor is connected to.
+/// - `D`: The delay used to wait for the measurement to finish.
+///
+/// # Constraints
+///
+/// - `I2C`: Must implement the async `I2c` trait.
+/// - `D`: Must implement the async `DelayNs` trait.
+pub struct Bmp280<I2C, D>
+where
+    I2C: I2c,
+    D: DelayNs,
+{
+    /// I2C bus used for communication with the sensor.
+    i2c: I2C,
+    /// Delay used to wait for the measurement to finish.
+    delay: D,
+    /// I2C address of the sensor.
+    address: u8,
+    /// Whether the sensor is a BME280 that also measures humidity.
+    has_humidity: bool,
+    /// Factory calibration values read during `init`.
+    calibration: Calibration,
+}
+
+impl<I2C, D> Bmp280<I2C, D>
+where
+    I2C: I2c,
+    D: DelayNs,
+{
+    /// Creates a new driver instance using the default BMP280 address.
+    pub fn new(i2c: I2C, delay: D) -> Self {
+        Self {
+            i2c,
+            delay,
+            address: BMP280_ADDRESS,
+            has_humidity: false,
+            calibration: Calibration::default(),
+        }
+    }
+
+    /// Release the I2C bus used by the driver
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    /// Read consecutive registers starting at `register`
+    async fn read_registers(&mut self, register: u8, data: &mut [u8]) -> Result<(), SensorError> {
+        self.i2c.write_read(self.address, &[register], data).await?;
+        Ok(())
+    }
+
+    /// Temperature compensation from the datasheet, returns t_fine and the
+    /// temperature in hundredths of a degree celsius
+    fn compensate_temperature(&self, adc_t: i32) -> (i32, i32) {
+        let c = &self.calibration;
+        let var1 = (((adc_t >> 3) - ((c.t1 as i32) << 1)) * c.t2 as i32) >> 11;
+        let var2 = (((((adc_t >> 4) - c.t1 as i32) * ((adc_t >> 4) - c.t1 as i32)) >> 12)
+            * c.t3 as i32)
+            >> 14;
+        let t_fine = var1 + var2;
+
+        (t_fine, (t_fine * 5 + 128) >> 8)
+    }
+
+    /// Pressure compensation from the datasheet, returns the pressure in pascal
+    fn compensate_pressure(&self, adc_p: i32, t_fine: i32) -> Option<u32> {
+        let c = &self.calibration;
+        let mut var1 = t_fine as i64 - 128000;
+        let mut var2 = var1 * var1 * c.p6 as i64;
+        var2 += (var1 * c.p5 as i64) << 17;
+        var2 += (c.p4 as i64) << 35;
+        var1 = ((var1 * var1 * c.p3 as i64) >> 8) + ((var1 * c.p2 as i64) << 12);
+        var1 = (((1i64 << 47) + var1) * c.p1 as i64) >> 33;
+
+        if var1 == 0 {
+            // Avoid a division by zero
+            return None;
+        }
+
+        let mut p = 1048576 - adc_p as i64;
+        p = (((p << 31) - var2) * 3125) / var1;
+        var1 = (c.p9 as i64 * (p >> 13) * (p >> 13)) >> 25;
+        var2 = (c.p8 as i64 * p) >> 19;
+        p = ((p + var1 + var2) >> 8) + ((c.p7 as i64) << 4);
+
+        // p is in Q24.8 format
+        Some((p >> 8) as u32)
+    }
+
+    /// Humidity compensation from the datasheet, returns the humidity in tenths of a percent
+    fn compensate_humidity(&self, adc_h: i32, t_fine: i32) -> u16 {
+        let c = &self.calibration;
+        let mut v = t_fine - 76800;
+        v = (((adc_h << 14) - ((c.h4 as i32) << 20) - (c.h5 as i32 * v) + 16384) >> 15)
+            * (((((((v * c.h6 as i32) >> 10) * (((v * c.h3 as i32) >> 11) + 32768)) >> 10)
+                + 2097152)
+                * c.h2 as i32
+                + 8192)
+                >> 14);
+        v -= ((((v >> 15) * (v >> 15)) >> 7) * c.h1 as i32) >> 4;
+        v = v.clamp(0, 419430400);
+
+        // v >> 12 is in Q22.10 format
+        (((v >> 12) as u32 * 10) >> 10) as u16
+    }
+}
+
+impl<I2C, D> EnvironmentSensor for Bmp280<I2C, D>
+where
+    I2C: I2c,
+    D: DelayNs,
+{
+    fn name(&self) -> &'static str {
+        if self.has_humidity {
+            "BME280"
+        } else {
+            "BMP280"
+        }
+    }
+
+    async fn init(&mut self) -> Result<(), SensorError> {
+        let mut chip_id = [0u8; 1];
+        self.read_registers(REG_CHIP_ID, &mut chip_id).await?;
+
+        self.has_humidity = match chip_id[0] {
+            CHIP_ID_BMP280 => false,
+            CHIP_ID_BME280 => true,
+            id => return Err(SensorError::UnknownChip(id)),
+        };
+
+        debug!("Read {} calibration", self.name());
+        let mut data = [0u8; 24];
+        self.read_registers(REG_CALIBRATION, &mut data).await?;
+
+        let u = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]);
+        let s = |i: usize| i16::from_le_bytes([data[i], data[i + 1]]);
+        let mut calibration = Calibration {
+            t1: u(0),
+            t2: s(2),
+            t3: s(4),
+            p1: u(6),
+            p2: s(8),
+            p3: s(10),
+            p4: s(12),
+            p5: s(14),
+            p6: s(16),
+            p7: s(18),
+            p8: s(20),
+            p9: s(22),
+            ..Calibration::default()
+        };
+
+        if self.has_humidity {
+            let mut h1 = [0u8; 1];
+            self.read_registers(REG_CALIBRATION_H1, &mut h1).await?;
+
+            let mut h = [0u8; 7];
+            self.read_registers(REG_CALIBRATION_H2, &mut h).await?;
+
+            // H4 and H5 are 12 bit values sharing the nibbles of register 0xe5
+            calibration.h1 = h1[0];
+            calibration.h2 = i16::from_le_bytes([h[0], h[1]]);
+            calibration.h3 = h[2];
+            calibration.h4 = ((h[3] as i8 as i16) << 4) | (h[4] & 0x0f) as i16;
+            calibration.h5 = ((h[5] as i8 as i16) << 4) | (h[4] >> 4) as i16;
+            calibration.h6 = h[6] as i8;
+        }
+
+        self.calibration = calibration;
+
+        Ok(())
+    }
+
+    async fn read(&mut self) -> Result<EnvironmentReading, SensorError> {
+        // The humidity control only takes effect after writing the measurement control
+        if self.has_humidity {
+            self.i2c
+                .write(self.address, &[REG_CTRL_HUM, CTRL_HUM_OVERSAMPLING_X1])
+                .await?;
+        }
+        self.i2c
+            .write(self.address, &[REG_CTRL_MEAS, CTRL_MEAS_FORCED_X1])
+            .await?;
+        self.delay.delay_ms(MEASUREMENT_TIME_MS).await;
+
+        // Pressure (20 bits), temperature (20 bits) and humidity (16 bits, BME280 only)
+        let mut data = [0u8; 8];
+        let len = if self.has_humidity { 8 } else { 6 };
+        self.read_registers(REG_DATA, &mut data[..len]).await?;
+
+        let adc_p = ((data[0] as i32) << 12) | ((data[1] as i32) << 4) | ((data[2] as i32) >> 4);
+        let adc_t = ((data[3] as i32) << 12) | ((data[4] as i32) << 4) | ((data[5] as i32) >> 4);
+        let adc_h = ((data[6] as i32) << 8) | data[7] as i32;
+
+        let (t_fine, temp_hundredths_celsius) = self.compensate_temperature(adc_t);
+        let pressure_pa = self.compensate_pressure(adc_p, t_fine);
+        let humidity_tenths = if self.has_humidity {
+            Some(self.compensate_humidity(adc_h, t_fine))
+        } else {
+            None
+        };
+
+        Ok(EnvironmentReading {
+            temp_tenths_celsius: (temp_hundredths_celsius / 10) as i16,
+            humidity_tenths,
+            pressure_pa,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::test_util::{block_on, I2cResponse, MockI2c, Recorder};
+
+    /// Calibration of the compensation example in the datasheet
+    const DATASHEET_CALIBRATION: Calibration = Calibration {
+        t1: 27504,
+        t2: 26435,
+        t3: -1000,
+        p1: 36477,
+        p2: -10685,
+        p3: 3024,
+        p4: 2855,
+        p5: 140,
+        p6: -7,
+        p7: 15500,
+        p8: -14600,
+        p9: 6000,
+        h1: 0,
+        h2: 0,
+        h3: 0,
+        h4: 0,
+        h5: 0,
+        h6: 0,
+    };
+
+    #[test]
+    fn compensation_matches_the_datasheet_example() {
+        let mut bmp280 = Bmp280::new(MockI2c::new([]), Recorder::default().delay());
+        bmp280.calibration = DATASHEET_CALIBRATION;
+
+        let (t_fine, temp_hundredths_celsius) = bmp280.compensate_temperature(519888);
+
+        assert_eq!(temp_hundredths_celsius, 2508);
+        assert_eq!(bmp280.compensate_pressure(415148, t_fine), Some(100653));
+    }
+
+    #[test]
+    fn pressure_without_calibration_is_none() {
+        let bmp280 = Bmp280::new(MockI2c::new([]), Recorder::default().delay());
+
+        assert_eq!(bmp280.compensate_pressure(415148, 0), None);
+    }
+
+    #[test]
+    fn read_unpacks_the_calibration_and_the_20_bit_values() {
+        let c = DATASHEET_CALIBRATION;
+        let calibration: Vec<u8> = [
+            c.t1.to_le_bytes(),
+            c.t2.to_le_bytes(),
+            c.t3.to_le_bytes(),
+            c.p1.to_le_bytes(),
+            c.p2.to_le_bytes(),
+            c.p3.to_le_bytes(),
+            c.p4.to_le_bytes(),
+            c.p5.to_le_bytes(),
+            c.p6.to_le_bytes(),
+            c.p7.to_le_bytes(),
+            c.p8.to_le_bytes(),
+            c.p9.to_le_bytes(),
+        ]
+        .concat();
+        let responses = [
+            I2cResponse::Read(vec![CHIP_ID_BMP280]),
+            I2cResponse::Read(calibration),
+            // Measurement control, then adc_P = 415148 and adc_T = 519888
+            I2cResponse::Read(vec![]),
+            I2cResponse::Read(vec![0x65, 0x5a, 0xc0, 0x7e, 0xed, 0x00]),
+        ];
+        let mut bmp280 = Bmp280::new(MockI2c::new(responses), Recorder::default().delay());
+
+        block_on(bmp280.init()).unwrap();
+        let reading = block_on(bmp280.read()).unwrap();
+
+        assert_eq!(
+            reading,
+            EnvironmentReading {
+                temp_tenths_celsius: 250,
+                humidity_tenths: None,
+                pressure_pa: Some(100653),
+            }
+        );
+        assert_eq!(
+            bmp280.release().writes,
+            [
+                vec![REG_CHIP_ID],
+                vec![REG_CALIBRATION],
+                vec![REG_CTRL_MEAS, CTRL_MEAS_FORCED_X1],
+                vec![REG_DATA],
+            ]
+        );
+    }
+
+    #[test]
+    fn init_rejects_an_unknown_chip() {
+        let responses = [I2cResponse::Read(vec![0x55])];
+        let mut bmp280 = Bmp280::new(MockI2c::new(responses), Recorder::default().delay());
+
+        assert_eq!(block_on(bmp280.init()), Err(SensorError::UnknownChip(0x55)));
+    }
+}
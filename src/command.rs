@@ -4,6 +4,18 @@
 /// Command Software Reset
 pub const SOFTWARE_RESET: u8 = 0x01;
 
+/// Command for Read Display Identification Information
+pub const READ_DISPLAY_ID: u8 = 0x04;
+
+/// Command for Read Display Status
+pub const READ_DISPLAY_STATUS: u8 = 0x09;
+
+/// Command for Read Display MADCTL
+pub const READ_DISPLAY_MADCTL: u8 = 0x0b;
+
+/// Command for Read Display Pixel Format
+pub const READ_DISPLAY_PIXEL_FORMAT: u8 = 0x0c;
+
 /// Command Memory Access Control
 pub const MEMORY_ACCESS_CONTROL: u8 = 0x36;
 
@@ -37,6 +49,12 @@ pub const PAGE_ADDRESS_SET: u8 = 0x2b;
 /// Command for MemoryWrite
 pub const MEMORY_WRITE: u8 = 0x2c;
 
+/// Command for Memory Read
+pub const MEMORY_READ: u8 = 0x2e;
+
+/// Command for Memory Read Continue
+pub const MEMORY_READ_CONTINUE: u8 = 0x3e;
+
 /// Command for Vertical Scroll Define
 pub const VERTICAL_SCROLL_DEFINE: u8 = 0x33;
 
@@ -52,6 +70,9 @@ pub const IDLE_MODE_ON: u8 = 0x39;
 /// Command for Set Brightness
 pub const SET_BRIGHTNESS: u8 = 0x51;
 
+/// Command for Write CTRL Display
+pub const WRITE_CTRL_DISPLAY: u8 = 0x53;
+
 /// Command for Content Adaptive Brightness
 pub const CONTENT_ADAPTIVE_BRIGHTNESS: u8 = 0x55;
 
@@ -62,7 +83,7 @@ pub const NORMAL_MODE_FRAME_RATE: u8 = 0xb1;
 pub const IDLE_MODE_FRAME_RATE: u8 = 0xb2;
 
 /// Command for Power A
-pub const POWER_A: u8 = 0xcf;
+pub const POWER_A: u8 = 0xcb;
 
 /// Command for Power B
 pub const POWER_B: u8 = 0xcf;
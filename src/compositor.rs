@@ -0,0 +1,368 @@
+use heapless::Vec;
+
+use embedded_graphics::{pixelcolor::Rgb565, prelude::*, primitives::Rectangle};
+
+use crate::double_buffer::DoubleBuffer;
+use crate::framebuffer::Framebuffer;
+use crate::widget::Widget;
+
+/// Part of the display drawn in the pixel buffer.
+///
+/// The tile is a [`DrawTarget`] in display coordinates, pixels outside of the
+/// tile are dropped so every layer can draw itself completely.
+pub struct Tile<'a> {
+    /// Pixels of the tile.
+    fb: Framebuffer<'a, Rgb565>,
+    /// Area of the display covered by the tile.
+    area: Rectangle,
+}
+
+impl<'a> Tile<'a> {
+    /// Creates a black tile covering `area` of the display.
+    ///
+    /// # Panics
+    ///
+    /// If the buffer cannot hold the tile.
+    pub fn new(buffer: &'a mut [u8], area: Rectangle) -> Self {
+        Self {
+            fb: Framebuffer::new(buffer, area.size),
+            area,
+        }
+    }
+
+    /// Give back the buffer holding the pixels of the tile, to send it to the display
+    pub fn into_buffer(self) -> &'a mut [u8] {
+        self.fb.release()
+    }
+}
+
+impl Dimensions for Tile<'_> {
+    fn bounding_box(&self) -> Rectangle {
+        self.area
+    }
+}
+
+impl DrawTarget for Tile<'_> {
+    type Color = Rgb565;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let area = self.area;
+        self.fb.draw_iter(
+            pixels
+                .into_iter()
+                .filter(|Pixel(point, _)| area.contains(*point))
+                .map(|Pixel(point, color)| Pixel(point - area.top_left, color)),
+        )
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.area);
+        self.fb
+            .fill_solid(&area.translate(-self.area.top_left), color)
+    }
+}
+
+/// Element of the screen drawn by the [`Compositor`].
+///
+/// Every [`Widget`] is a layer, other elements can implement it to be drawn
+/// over or under the widgets.
+pub trait Layer {
+    /// Area of the display the layer draws in
+    fn area(&self) -> Rectangle;
+
+    /// Area of the layer that changed since the last call, the layer is then clean
+    fn take_dirty_area(&mut self) -> Option<Rectangle>;
+
+    /// Draw the part of the layer inside the tile
+    fn draw_tile(&self, tile: &mut Tile<'_>);
+}
+
+impl<W> Layer for W
+where
+    W: Widget,
+{
+    fn area(&self) -> Rectangle {
+        self.bounding_box()
+    }
+
+    fn take_dirty_area(&mut self) -> Option<Rectangle> {
+        let area = self.dirty_area();
+        self.mark_clean();
+        area
+    }
+
+    fn draw_tile(&self, tile: &mut Tile<'_>) {
+        self.draw(tile);
+    }
+}
+
+/// Redraws the changed areas of a stack of layers through a small pixel buffer.
+///
+/// The areas that changed are kept as up to `N` dirty rectangles, rectangles that
+/// overlap or touch are merged. Every dirty rectangle is redrawn in tiles that fit
+/// in the pixel buffer. For every tile all layers that intersect it are drawn back
+/// to front, so a layer drawn over another one, e.g. text on a rounded rectangle
+/// with transparent corners, shows what is below it.
+pub struct Compositor<const N: usize> {
+    /// Area of the display, dirty rectangles are clipped to it.
+    screen: Rectangle,
+    /// Areas of the display that have to be redrawn.
+    dirty: Vec<Rectangle, N>,
+}
+
+impl<const N: usize> Compositor<N> {
+    /// Creates a compositor for a display of `size` pixels, nothing is dirty.
+    pub const fn new(size: Size) -> Self {
+        Self {
+            screen: Rectangle::new(Point::zero(), size),
+            dirty: Vec::new(),
+        }
+    }
+
+    /// Mark an area of the display to be redrawn by the next render
+    pub fn invalidate(&mut self, area: Rectangle) {
+        let mut area = area.intersection(&self.screen);
+        if area.is_zero_sized() {
+            return;
+        }
+
+        // Merging can make the area reach rectangles it did not touch before
+        let mut i = 0;
+        while i < self.dirty.len() {
+            if should_merge(&self.dirty[i], &area) {
+                area = union(&self.dirty.swap_remove(i), &area);
+                i = 0;
+            } else {
+                i += 1;
+            }
+        }
+
+        if self.dirty.is_full() {
+            // Merge with the rectangle that needs the least extra pixels redrawn
+            let mut best = 0;
+            for i in 1..self.dirty.len() {
+                if merge_cost(&self.dirty[i], &area) < merge_cost(&self.dirty[best], &area) {
+                    best = i;
+                }
+            }
+
+            let merged = union(&self.dirty.swap_remove(best), &area);
+            self.invalidate(merged);
+            return;
+        }
+
+        let _ = self.dirty.push(area);
+    }
+
+    /// Redraw the changed areas of the layers on the display.
+    ///
+    /// # Parameters
+    ///
+    /// - `double_buffer`: Pixel buffers the tiles are drawn in and sent to the display.
+    /// - `layers`: All layers of the screen, from back to front.
+    ///
+    /// # Panics
+    ///
+    /// If the buffers cannot hold a single pixel.
+    pub async fn render(
+        &mut self,
+        double_buffer: &mut DoubleBuffer,
+        layers: &mut [&mut dyn Layer],
+    ) {
+        for layer in layers.iter_mut() {
+            if let Some(area) = layer.take_dirty_area() {
+                self.invalidate(area);
+            }
+        }
+
+        let max_pixels = double_buffer.buffer_pixels() as u32;
+        assert!(max_pixels > 0);
+
+        while let Some(area) = self.dirty.pop() {
+            for tile_area in tiles(area, max_pixels) {
+                let mut tile = Tile::new(double_buffer.acquire().await, tile_area);
+                for layer in layers.iter() {
+                    if !layer.area().intersection(&tile_area).is_zero_sized() {
+                        layer.draw_tile(&mut tile);
+                    }
+                }
+
+                // The next tile is drawn in the other buffer while this one is sent
+                double_buffer.submit(tile.into_buffer(), tile_area).await;
+            }
+        }
+    }
+}
+
+/// Split an area in tiles of at most `max_pixels` pixels, row by row.
+///
+/// The tiles are as wide as the area if a row fits, otherwise they are one row high.
+pub fn tiles(area: Rectangle, max_pixels: u32) -> impl Iterator<Item = Rectangle> {
+    let tile_width = area.size.width.min(max_pixels).max(1);
+    let tile_height = (max_pixels / tile_width).clamp(1, area.size.height.max(1));
+
+    (0..area.size.height)
+        .step_by(tile_height as usize)
+        .flat_map(move |y| {
+            (0..area.size.width)
+                .step_by(tile_width as usize)
+                .map(move |x| {
+                    Rectangle::new(
+                        area.top_left + Point::new(x as i32, y as i32),
+                        Size::new(
+                            tile_width.min(area.size.width - x),
+                            tile_height.min(area.size.height - y),
+                        ),
+                    )
+                })
+        })
+}
+
+/// Number of pixels in the rectangle
+fn pixel_count(area: &Rectangle) -> u32 {
+    area.size.width * area.size.height
+}
+
+/// Smallest rectangle containing both rectangles, neither may be zero sized
+fn union(a: &Rectangle, b: &Rectangle) -> Rectangle {
+    Rectangle::with_corners(
+        a.top_left.component_min(b.top_left),
+        a.bottom_right()
+            .unwrap_or(a.top_left)
+            .component_max(b.bottom_right().unwrap_or(b.top_left)),
+    )
+}
+
+/// Number of pixels redrawn by merging the rectangles that are not in either of them
+fn merge_cost(a: &Rectangle, b: &Rectangle) -> u32 {
+    let overlap = pixel_count(&a.intersection(b));
+    (pixel_count(&union(a, b)) + overlap).saturating_sub(pixel_count(a) + pixel_count(b))
+}
+
+/// Rectangles are merged when they overlap, so no pixel is drawn twice, or when the
+/// union covers no pixels outside of them, e.g. side by side with the same height
+fn should_merge(a: &Rectangle, b: &Rectangle) -> bool {
+    !a.intersection(b).is_zero_sized() || merge_cost(a, b) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> Rectangle {
+        Rectangle::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    /// Dirty rectangles after invalidating the areas, in any order
+    fn dirty<const N: usize>(areas: &[Rectangle]) -> Vec<Rectangle> {
+        let mut compositor = Compositor::<N>::new(Size::new(320, 240));
+        for area in areas {
+            compositor.invalidate(*area);
+        }
+        let mut dirty = compositor.dirty.to_vec();
+        dirty.sort_by_key(|area| (area.top_left.y, area.top_left.x));
+        dirty
+    }
+
+    #[test]
+    fn overlapping_and_adjacent_rectangles_are_merged() {
+        // Overlapping
+        assert_eq!(
+            dirty::<4>(&[rect(0, 0, 10, 10), rect(5, 5, 10, 10)]),
+            [rect(0, 0, 15, 15)]
+        );
+        // Side by side with the same height, and stacked with the same width
+        assert_eq!(
+            dirty::<4>(&[rect(0, 0, 10, 10), rect(10, 0, 5, 10)]),
+            [rect(0, 0, 15, 10)]
+        );
+        assert_eq!(
+            dirty::<4>(&[rect(0, 0, 10, 10), rect(0, 10, 10, 3)]),
+            [rect(0, 0, 10, 13)]
+        );
+        // A rectangle bridging two others merges all three
+        assert_eq!(
+            dirty::<4>(&[rect(0, 0, 10, 10), rect(20, 0, 10, 10), rect(10, 0, 10, 10)]),
+            [rect(0, 0, 30, 10)]
+        );
+    }
+
+    #[test]
+    fn separate_rectangles_are_kept_apart() {
+        // Touching at a corner, a gap, and side by side with different heights
+        assert_eq!(
+            dirty::<4>(&[rect(0, 0, 10, 10), rect(10, 10, 5, 5), rect(30, 0, 5, 5)]),
+            [rect(0, 0, 10, 10), rect(30, 0, 5, 5), rect(10, 10, 5, 5)]
+        );
+        assert_eq!(
+            dirty::<4>(&[rect(0, 0, 10, 10), rect(10, 0, 5, 9)]),
+            [rect(0, 0, 10, 10), rect(10, 0, 5, 9)]
+        );
+    }
+
+    #[test]
+    fn merge_cost_counts_the_pixels_outside_of_both_rectangles() {
+        assert_eq!(merge_cost(&rect(0, 0, 10, 10), &rect(10, 0, 5, 10)), 0);
+        assert_eq!(merge_cost(&rect(0, 0, 10, 10), &rect(10, 10, 5, 5)), 100);
+        // Overlapping pixels are only counted once
+        assert_eq!(merge_cost(&rect(0, 0, 10, 10), &rect(5, 0, 10, 10)), 0);
+        assert_eq!(merge_cost(&rect(0, 0, 10, 10), &rect(5, 5, 10, 10)), 50);
+
+        assert!(should_merge(&rect(0, 0, 10, 10), &rect(5, 5, 10, 10)));
+        assert!(should_merge(&rect(0, 0, 10, 10), &rect(10, 0, 5, 10)));
+        assert!(!should_merge(&rect(0, 0, 10, 10), &rect(10, 10, 5, 5)));
+        assert!(!should_merge(&rect(0, 0, 10, 10), &rect(11, 0, 5, 10)));
+    }
+
+    #[test]
+    fn a_full_list_merges_with_the_cheapest_rectangle() {
+        assert_eq!(
+            dirty::<2>(&[rect(0, 0, 2, 2), rect(100, 100, 2, 2), rect(10, 0, 2, 2)]),
+            [rect(0, 0, 12, 2), rect(100, 100, 2, 2)]
+        );
+
+        // The merged rectangle can overlap the other rectangle, which is merged too
+        assert_eq!(
+            dirty::<2>(&[rect(5, 2, 2, 2), rect(4, 4, 4, 4), rect(2, 1, 2, 4)]),
+            [rect(2, 1, 6, 7)]
+        );
+    }
+
+    #[test]
+    fn areas_are_clipped_to_the_screen() {
+        assert_eq!(dirty::<4>(&[rect(-5, 230, 20, 20)]), [rect(0, 230, 15, 10)]);
+        assert_eq!(dirty::<4>(&[rect(320, 0, 10, 10), rect(0, 0, 0, 5)]), []);
+    }
+
+    #[test]
+    fn tiles_cover_the_area_in_rows() {
+        let tiles = |area, max_pixels| tiles(area, max_pixels).collect::<Vec<_>>();
+
+        // Whole rows, the last tile has the rows that are left
+        assert_eq!(
+            tiles(rect(5, 7, 10, 5), 25),
+            [rect(5, 7, 10, 2), rect(5, 9, 10, 2), rect(5, 11, 10, 1)]
+        );
+
+        // A row does not fit, the tiles are one row high and the last one is narrower
+        assert_eq!(
+            tiles(rect(0, 0, 30, 2), 12),
+            [
+                rect(0, 0, 12, 1),
+                rect(12, 0, 12, 1),
+                rect(24, 0, 6, 1),
+                rect(0, 1, 12, 1),
+                rect(12, 1, 12, 1),
+                rect(24, 1, 6, 1),
+            ]
+        );
+
+        assert_eq!(tiles(rect(0, 0, 4, 4), 1000), [rect(0, 0, 4, 4)]);
+    }
+}
@@ -0,0 +1,208 @@
+use embedded_hal::digital::OutputPin;
+use embedded_hal::spi::SpiDevice as BlockingSpiDevice;
+use embedded_hal_async::spi::SpiDevice;
+
+use embedded_graphics::{
+    mono_font::MonoTextStyle,
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::Rectangle,
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+};
+
+use crate::backlight::Backlight;
+use crate::ili9341_async::{Error, Ili9341};
+
+/// Distance between the left edge of the display and the text of a line
+const CONSOLE_TEXT_INSET: i32 = 7;
+
+/// Rolling log of text lines using the hardware vertical scrolling of the display.
+///
+/// The console covers the full width of the display and is the vertical scrolling
+/// area, the rows above and below it do not move. The lines are kept in the display
+/// memory, a new line overwrites the oldest one and the display is scrolled by a
+/// line so it shows up at the bottom. Only the new line is sent to the display.
+///
+/// The lines are drawn directly on the display, so the console cannot share its
+/// rows with other widgets.
+pub struct Console {
+    /// First row of the console on the display.
+    top: u16,
+    /// Number of lines shown.
+    lines: u16,
+    /// Font and colors of the text, the background color fills the whole line.
+    style: MonoTextStyle<'static, Rgb565>,
+    /// Row of the console shown at its top, where the oldest line starts.
+    scroll: u16,
+}
+
+impl Console {
+    /// Creates a console of `lines` lines starting at row `top`.
+    pub fn new(top: u16, lines: u16, style: MonoTextStyle<'static, Rgb565>) -> Self {
+        Self {
+            top,
+            lines,
+            style,
+            scroll: 0,
+        }
+    }
+
+    /// Height of a line in pixels
+    fn line_height(&self) -> u16 {
+        self.style.font.character_size.height as u16
+    }
+
+    /// Height of the console in pixels
+    pub fn height(&self) -> u16 {
+        self.lines * self.line_height()
+    }
+
+    /// Make the console the scrolling area of the display and clear it.
+    ///
+    /// # Errors
+    ///
+    /// - `Error::OutOfBounds` if the console does not fit on the display, nothing is sent.
+    /// - Any error of the commands to the display.
+    pub async fn setup<SPI, DC, RST, PO>(
+        &mut self,
+        display: &mut Ili9341<SPI, DC, RST, PO>,
+    ) -> Result<(), Error>
+    where
+        SPI: SpiDevice + BlockingSpiDevice,
+        DC: OutputPin,
+        RST: OutputPin,
+        PO: Backlight,
+    {
+        let area = Rectangle::new(
+            Point::new(0, self.top as i32),
+            Size::new(display.width as u32, self.height() as u32),
+        );
+        let bottom = self
+            .top
+            .checked_add(self.height())
+            .filter(|bottom| *bottom as usize <= display.height)
+            .ok_or(Error::OutOfBounds { area })?;
+
+        // The scrolling area is defined in rows of the display memory
+        let top_fixed = display.top_memory_row() + self.top;
+        let bottom_fixed = display.memory_rows() - (display.top_memory_row() + bottom);
+        display
+            .define_scroll_area(top_fixed, self.height(), bottom_fixed)
+            .await?;
+
+        self.scroll = 0;
+        display.scroll_to(self.scroll).await?;
+
+        display.fill_solid(&area, self.background())?;
+
+        Ok(())
+    }
+
+    /// Add a line at the bottom of the console, the oldest line scrolls out at the top.
+    ///
+    /// Text longer than the display width is cut off.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any commands to the display fails
+    pub async fn push_line<SPI, DC, RST, PO>(
+        &mut self,
+        display: &mut Ili9341<SPI, DC, RST, PO>,
+        text: &str,
+    ) -> Result<(), Error>
+    where
+        SPI: SpiDevice + BlockingSpiDevice,
+        DC: OutputPin,
+        RST: OutputPin,
+        PO: Backlight,
+    {
+        if self.lines == 0 {
+            return Ok(());
+        }
+
+        // The oldest line is at the top of the scrolled console, overwrite it
+        let row = (self.top + self.scroll) as i32;
+        let line = Rectangle::new(
+            Point::new(0, row),
+            Size::new(display.width as u32, self.line_height() as u32),
+        );
+        display.fill_solid(&line, self.background())?;
+
+        let text_style = TextStyleBuilder::new()
+            .alignment(Alignment::Left)
+            .baseline(Baseline::Top)
+            .build();
+        Text::with_text_style(
+            text,
+            Point::new(CONSOLE_TEXT_INSET, row),
+            self.style,
+            text_style,
+        )
+        .draw(display)?;
+
+        // Scroll the new line from the top to the bottom of the console
+        self.scroll = (self.scroll + self.line_height()) % self.height();
+        display.scroll_to(self.scroll).await
+    }
+
+    /// Color of the console without text
+    fn background(&self) -> Rgb565 {
+        self.style.background_color.unwrap_or(Rgb565::BLACK)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+
+    use embedded_graphics::mono_font::ascii::FONT_6X10;
+
+    use super::*;
+    use crate::command;
+    use crate::ili9341_async::Config;
+    use crate::test_util::{block_on, mock_display, Event};
+
+    #[test]
+    fn setup_rejects_a_console_below_the_display() {
+        let (mut display, mut delay, recorder) = mock_display(Config::default());
+        block_on(display.initialize(&mut delay)).unwrap();
+        recorder.take();
+
+        let style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+        let mut console = Console::new(200, 5, style);
+
+        assert_eq!(
+            block_on(console.setup(&mut display)),
+            Err(Error::OutOfBounds {
+                area: Rectangle::new(Point::new(0, 200), Size::new(320, 50))
+            })
+        );
+        assert!(recorder.take().is_empty());
+    }
+
+    #[test]
+    fn setup_defines_the_scroll_area_in_memory_rows() {
+        // A 240x240 panel in the middle of the 240x320 memory of an ILI9341
+        let config = Config::builder()
+            .panel_size(240, 240)
+            .memory_size(240, 320)
+            .offset(0, 40)
+            .build()
+            .unwrap();
+        let (mut display, mut delay, recorder) = mock_display(config);
+        block_on(display.initialize(&mut delay)).unwrap();
+        recorder.take();
+
+        let style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+        let mut console = Console::new(100, 5, style);
+        block_on(console.setup(&mut display)).unwrap();
+
+        assert_eq!(
+            recorder.take()[0],
+            Event::Command {
+                command: command::VERTICAL_SCROLL_DEFINE,
+                params: vec![0, 140, 0, 50, 0, 130]
+            }
+        );
+    }
+}
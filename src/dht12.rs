@@ -0,0 +1,344 @@
+use log::*;
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::sensor::{EnvironmentReading, EnvironmentSensor, SensorError, TemperatureUnit};
+
+/// I2C address of the DHT12 sensor
+pub const DHT12_ADDRESS: u8 = 0x5c;
+
+/// Register the sensor data starts at
+const DATA_REGISTER: u8 = 0x00;
+
+/// Lowest temperature the DHT12 can measure in tenths of a degree celsius (datasheet)
+const MIN_TEMP_TENTHS_CELSIUS: i16 = -200;
+
+/// Highest temperature the DHT12 can measure in tenths of a degree celsius (datasheet)
+const MAX_TEMP_TENTHS_CELSIUS: i16 = 600;
+
+/// Highest relative humidity value in tenths of a percent that makes sense
+const MAX_HUMIDITY_TENTHS: u16 = 1000;
+
+/// Bit in the temperature scale byte that marks a negative temperature
+const TEMP_SIGN_BIT: u8 = 0x80;
+
+/// A single reading from the DHT12 sensor
+///
+/// The values are stored as fixed point integers in tenths, which is the resolution
+/// of the sensor, so no precision is lost until a conversion is requested. The
+/// conversions are those of [`EnvironmentReading`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dht12Reading {
+    /// Relative humidity in tenths of a percent
+    pub humidity_tenths: u16,
+    /// Temperature in tenths of a degree celsius
+    pub temp_tenths_celsius: i16,
+}
+
+impl Dht12Reading {
+    /// Relative humidity in percent
+    pub fn humidity(&self) -> f32 {
+        self.humidity_tenths as f32 / 10.0
+    }
+
+    /// Temperature in degrees celsius
+    pub fn celsius(&self) -> f32 {
+        EnvironmentReading::from(*self).celsius()
+    }
+
+    /// Temperature in degrees fahrenheit
+    pub fn fahrenheit(&self) -> f32 {
+        EnvironmentReading::from(*self).fahrenheit()
+    }
+
+    /// Temperature in kelvin
+    pub fn kelvin(&self) -> f32 {
+        EnvironmentReading::from(*self).kelvin()
+    }
+
+    /// Temperature in the requested unit
+    pub fn temperature(&self, unit: TemperatureUnit) -> f32 {
+        EnvironmentReading::from(*self).temperature(unit)
+    }
+}
+
+/// DHT12 async temperature and humidity sensor driver.
+///
+/// # Type Parameters
+///
+/// - `I2C`: The I2C bus the sensor is connected to.
+///
+/// # Constraints
+///
+/// - `I2C`: Must implement the async `I2c` trait.
+pub struct Dht12<I2C>
+where
+    I2C: I2c,
+{
+    /// I2C bus used for communication with the sensor.
+    i2c: I2C,
+    /// I2C address of the sensor.
+    address: u8,
+}
+
+impl<I2C> Dht12<I2C>
+where
+    I2C: I2c,
+{
+    /// Creates a new driver instance using the default DHT12 address.
+    pub fn new(i2c: I2C) -> Self {
+        Self {
+            i2c,
+            address: DHT12_ADDRESS,
+        }
+    }
+
+    /// Release the I2C bus used by the driver
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    /// Read the humidity and temperature from the sensor.
+    ///
+    /// The five data bytes are read starting at register 0x00, the last byte being
+    /// the checksum of the first four.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the I2C transfer fails, the checksum does not match or
+    /// the decoded values are outside the range the sensor can measure.
+    pub async fn read(&mut self) -> Result<Dht12Reading, Dht12Error> {
+        let mut data = [0u8; 5];
+        self.i2c
+            .write_read(self.address, &[DATA_REGISTER], &mut data)
+            .await?;
+
+        trace!(
+            "DHT12  B0:{:02x?}  B1:{:02x?}  B2:{:02x?}  B3:{:02x?}  B4:{:02x?}",
+            data[0],
+            data[1],
+            data[2],
+            data[3],
+            data[4]
+        );
+
+        Self::decode(&data)
+    }
+
+    /// Verify the checksum and convert the raw sensor bytes into a reading.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the checksum does not match or the decoded values are
+    /// outside the range the sensor can measure.
+    pub fn decode(data: &[u8; 5]) -> Result<Dht12Reading, Dht12Error> {
+        let expected = checksum(data);
+        if expected != data[4] {
+            return Err(Dht12Error::Checksum {
+                expected,
+                actual: data[4],
+            });
+        }
+
+        // The integral part is in the first byte and the tenths in the second byte of
+        // each value. The sign of the temperature is the top bit of the tenths byte.
+        let humidity_tenths = decode_tenths(data[0], data[1])?;
+        let magnitude = decode_tenths(data[2], data[3] & !TEMP_SIGN_BIT)? as i16;
+        let temp_tenths_celsius = if (data[3] & TEMP_SIGN_BIT) != 0 {
+            -magnitude
+        } else {
+            magnitude
+        };
+
+        if humidity_tenths > MAX_HUMIDITY_TENTHS
+            || !(MIN_TEMP_TENTHS_CELSIUS..=MAX_TEMP_TENTHS_CELSIUS).contains(&temp_tenths_celsius)
+        {
+            return Err(Dht12Error::OutOfRange);
+        }
+
+        Ok(Dht12Reading {
+            humidity_tenths,
+            temp_tenths_celsius,
+        })
+    }
+}
+
+impl From<Dht12Reading> for EnvironmentReading {
+    fn from(reading: Dht12Reading) -> Self {
+        Self {
+            temp_tenths_celsius: reading.temp_tenths_celsius,
+            humidity_tenths: Some(reading.humidity_tenths),
+            pressure_pa: None,
+        }
+    }
+}
+
+impl<I2C> EnvironmentSensor for Dht12<I2C>
+where
+    I2C: I2c,
+{
+    fn name(&self) -> &'static str {
+        "DHT12"
+    }
+
+    async fn read(&mut self) -> Result<EnvironmentReading, SensorError> {
+        Ok(Dht12::read(self).await?.into())
+    }
+}
+
+/// Combine an integral byte and a tenths byte into a value in tenths
+fn decode_tenths(integral: u8, tenths: u8) -> Result<u16, Dht12Error> {
+    if tenths > 9 {
+        return Err(Dht12Error::OutOfRange);
+    }
+
+    Ok(integral as u16 * 10 + tenths as u16)
+}
+
+/// Checksum of the DHT12 data, the lower 8 bits of the sum of the first four bytes
+fn checksum(data: &[u8; 5]) -> u8 {
+    data[..4].iter().fold(0u8, |sum, b| sum.wrapping_add(*b))
+}
+
+/******************************************************************************************************
+*                                  IMPLEMENT CUSTOM ERRORS
+*****************************************************************************************************/
+
+use embedded_hal::i2c::Error as I2cError;
+use embedded_hal::i2c::ErrorKind as I2cErrorKind;
+
+/// Error Types used within this driver
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dht12Error {
+    /// An error in the underlying I2C bus
+    I2c(I2cErrorKind),
+
+    /// The checksum byte sent by the sensor does not match the data
+    Checksum { expected: u8, actual: u8 },
+
+    /// The sensor returned a value outside of its measurement range
+    OutOfRange,
+}
+
+impl<E> From<E> for Dht12Error
+where
+    E: I2cError,
+{
+    fn from(error: E) -> Self {
+        Self::I2c(error.kind())
+    }
+}
+
+impl From<Dht12Error> for SensorError {
+    fn from(error: Dht12Error) -> Self {
+        match error {
+            Dht12Error::I2c(kind) => Self::I2c(kind),
+            Dht12Error::Checksum { .. } => Self::Checksum,
+            Dht12Error::OutOfRange => Self::OutOfRange,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+
+    use super::*;
+    use crate::test_util::{block_on, I2cResponse, MockI2c};
+
+    /// Sensor answering the reads with the data
+    fn sensor(responses: impl IntoIterator<Item = I2cResponse>) -> Dht12<MockI2c> {
+        Dht12::new(MockI2c::new(responses))
+    }
+
+    /// The data bytes with a valid checksum
+    fn frame(data: [u8; 4]) -> I2cResponse {
+        let sum = data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+        I2cResponse::Read([&data[..], &[sum]].concat())
+    }
+
+    #[test]
+    fn read_valid_frame() {
+        let mut dht12 = sensor([frame([45, 3, 21, 5])]);
+
+        let reading = block_on(dht12.read()).unwrap();
+
+        assert_eq!(
+            reading,
+            Dht12Reading {
+                humidity_tenths: 453,
+                temp_tenths_celsius: 215,
+            }
+        );
+        assert_eq!(dht12.release().writes, [vec![DATA_REGISTER]]);
+    }
+
+    #[test]
+    fn read_bad_checksum() {
+        let mut dht12 = sensor([I2cResponse::Read(vec![45, 3, 21, 5, 0x4b])]);
+
+        assert_eq!(
+            block_on(dht12.read()),
+            Err(Dht12Error::Checksum {
+                expected: 0x4a,
+                actual: 0x4b
+            })
+        );
+    }
+
+    #[test]
+    fn read_tenths_out_of_range() {
+        let mut dht12 = sensor([frame([45, 10, 21, 5]), frame([45, 3, 21, 0x0a])]);
+
+        assert_eq!(block_on(dht12.read()), Err(Dht12Error::OutOfRange));
+        assert_eq!(block_on(dht12.read()), Err(Dht12Error::OutOfRange));
+    }
+
+    #[test]
+    fn read_i2c_error() {
+        let mut dht12 = sensor([I2cResponse::Nack]);
+
+        assert_eq!(
+            block_on(dht12.read()),
+            Err(Dht12Error::I2c(I2cErrorKind::NoAcknowledge(
+                embedded_hal::i2c::NoAcknowledgeSource::Address
+            )))
+        );
+    }
+
+    #[test]
+    fn decode_temperature_table() {
+        // Integral byte, tenths byte with the sign in bit 7, celsius, fahrenheit, kelvin
+        let table = [
+            (20, 0x80, -200, -4.0, 253.15),
+            (0, 0x85, -5, 31.1, 272.65),
+            (0, 0x00, 0, 32.0, 273.15),
+            (59, 0x09, 599, 139.82, 333.05),
+        ];
+
+        for (integral, tenths, celsius, fahrenheit, kelvin) in table {
+            let data = [50, 0, integral, tenths];
+            let mut dht12 = sensor([frame(data)]);
+            let reading = block_on(dht12.read()).unwrap();
+            assert_eq!(reading.temp_tenths_celsius, celsius, "{data:?}");
+            assert_eq!(reading.humidity_tenths, 500);
+
+            let close = |a: f32, b: f32| (a - b).abs() < 0.01;
+            assert!(close(reading.celsius(), celsius as f32 / 10.0), "{data:?}");
+            assert!(close(reading.fahrenheit(), fahrenheit), "{data:?}");
+            assert!(close(reading.kelvin(), kelvin), "{data:?}");
+            assert!(
+                close(reading.temperature(TemperatureUnit::Kelvin), kelvin),
+                "{data:?}"
+            );
+            assert_eq!(reading.humidity(), 50.0);
+        }
+    }
+
+    #[test]
+    fn decode_negative_zero() {
+        let mut dht12 = sensor([frame([50, 0, 0, 0x80])]);
+
+        assert_eq!(block_on(dht12.read()).unwrap().temp_tenths_celsius, 0);
+    }
+}
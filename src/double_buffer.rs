@@ -0,0 +1,246 @@
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embassy_sync::channel::Channel;
+
+use embedded_graphics::{pixelcolor::Rgb565, primitives::Rectangle};
+
+use crate::pixel_format::InterfaceColor;
+
+/// Pixel buffer handed back and forth between the renderer and the flush task.
+///
+/// Only one side owns a buffer at any time, so the renderer can never draw in a
+/// buffer that is still being sent to the display.
+pub type PixelBuffer = &'static mut [u8];
+
+/// Number of pixel buffers, one is drawn while the other one is sent
+const BUFFER_COUNT: usize = 2;
+
+/// Bytes per pixel in the buffers.
+///
+/// The buffers always hold `Rgb565` pixels, the renderers draw in `Rgb565` and the
+/// display the flush task sends them to must use the `Rgb565` interface format.
+pub const BYTES_PER_PIXEL: usize = Rgb565::BYTES_PER_PIXEL;
+
+/// Number of bytes of the pixels of `area`
+fn area_len(area: &Rectangle) -> usize {
+    area.size.width as usize * area.size.height as usize * BYTES_PER_PIXEL
+}
+
+/// A drawn buffer waiting to be sent to the display
+pub struct Flush {
+    /// Pixels in Rgb565 format, the first `area` pixels are sent.
+    buffer: PixelBuffer,
+    /// Area of the display the pixels are sent to.
+    area: Rectangle,
+}
+
+impl Flush {
+    /// Area of the display the pixels are sent to
+    pub fn area(&self) -> Rectangle {
+        self.area
+    }
+
+    /// Pixels to send to the display, [`BYTES_PER_PIXEL`] bytes per pixel
+    pub fn data(&self) -> &[u8] {
+        &self.buffer[..area_len(&self.area)]
+    }
+}
+
+/// Channels the buffers travel through between the renderer and the flush task
+pub struct FlushChannels {
+    /// Buffers the renderer can draw in.
+    free: Channel<NoopRawMutex, PixelBuffer, BUFFER_COUNT>,
+    /// Buffers waiting to be sent to the display.
+    filled: Channel<NoopRawMutex, Flush, BUFFER_COUNT>,
+}
+
+impl Default for FlushChannels {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FlushChannels {
+    /// Creates empty channels, the buffers are added by [`FlushChannels::split`].
+    pub const fn new() -> Self {
+        Self {
+            free: Channel::new(),
+            filled: Channel::new(),
+        }
+    }
+
+    /// Hand the two pixel buffers to the renderer half and create the flush half.
+    ///
+    /// # Panics
+    ///
+    /// If the buffers do not have the same size.
+    pub fn split(
+        &'static self,
+        first: PixelBuffer,
+        second: PixelBuffer,
+    ) -> (DoubleBuffer, Flusher) {
+        assert_eq!(first.len(), second.len());
+        let buffer_len = first.len();
+
+        let _ = self.free.try_send(first);
+        let _ = self.free.try_send(second);
+
+        (
+            DoubleBuffer {
+                channels: self,
+                buffer_len,
+            },
+            Flusher { channels: self },
+        )
+    }
+}
+
+/// Renderer half of a ping-pong pair of pixel buffers.
+///
+/// The renderer acquires a buffer, draws in it and submits it to be sent to the
+/// display. The submit returns as soon as the buffer is queued, so the renderer
+/// draws the next strip in the other buffer while the first one is being sent by
+/// the [`Flusher`] in another task.
+pub struct DoubleBuffer {
+    channels: &'static FlushChannels,
+    buffer_len: usize,
+}
+
+impl DoubleBuffer {
+    /// Size of each buffer in bytes
+    pub fn buffer_len(&self) -> usize {
+        self.buffer_len
+    }
+
+    /// Number of pixels each buffer holds
+    pub fn buffer_pixels(&self) -> usize {
+        self.buffer_len / BYTES_PER_PIXEL
+    }
+
+    /// Take a buffer to draw in, waits until one has been sent to the display
+    pub async fn acquire(&mut self) -> PixelBuffer {
+        self.channels.free.receive().await
+    }
+
+    /// Queue a drawn buffer to be sent to `area` of the display.
+    ///
+    /// # Panics
+    ///
+    /// If the buffer is too small for the area.
+    pub async fn submit(&mut self, buffer: PixelBuffer, area: Rectangle) {
+        assert!(buffer.len() >= area_len(&area));
+        self.channels.filled.send(Flush { buffer, area }).await;
+    }
+
+    /// Give back a buffer that was acquired but not drawn in
+    pub fn release(&mut self, buffer: PixelBuffer) {
+        let _ = self.channels.free.try_send(buffer);
+    }
+
+    /// Wait until all submitted buffers have been sent to the display, e.g. before
+    /// sending other commands to the display
+    pub async fn wait_idle(&mut self) {
+        let first = self.acquire().await;
+        let second = self.acquire().await;
+        self.release(first);
+        self.release(second);
+    }
+}
+
+/// Flush task half of a ping-pong pair of pixel buffers.
+///
+/// ```ignore
+/// loop {
+///     let flush = flusher.receive().await;
+///     display.write_pixels(flush.data(), flush.area()).await;
+///     flusher.release(flush);
+/// }
+/// ```
+pub struct Flusher {
+    channels: &'static FlushChannels,
+}
+
+impl Flusher {
+    /// Wait for the next buffer to send to the display
+    pub async fn receive(&mut self) -> Flush {
+        self.channels.filled.receive().await
+    }
+
+    /// Hand a sent buffer back to the renderer
+    pub fn release(&mut self, flush: Flush) {
+        let _ = self.channels.free.try_send(flush.buffer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+
+    use embedded_graphics::prelude::*;
+
+    use super::*;
+    use crate::test_util::{block_on, double_buffer};
+
+    #[test]
+    fn acquire_waits_until_a_sent_buffer_is_released() {
+        let (mut double_buffer, mut flusher) = double_buffer(8);
+        let mut cx = Context::from_waker(Waker::noop());
+        let (first_area, second_area) = (
+            Rectangle::new(Point::zero(), Size::new(2, 2)),
+            Rectangle::new(Point::new(2, 0), Size::new(1, 1)),
+        );
+
+        // Both buffers in flight
+        let first = block_on(double_buffer.acquire());
+        first.fill(1);
+        block_on(double_buffer.submit(first, first_area));
+        let second = block_on(double_buffer.acquire());
+        second.fill(2);
+        block_on(double_buffer.submit(second, second_area));
+
+        let mut acquire = pin!(double_buffer.acquire());
+        assert!(acquire.as_mut().poll(&mut cx).is_pending());
+
+        // The buffers are sent in the order they were submitted, with the pixels of the area
+        let flush = block_on(flusher.receive());
+        assert_eq!((flush.area(), flush.data()), (first_area, &[1; 8][..]));
+        assert!(acquire.as_mut().poll(&mut cx).is_pending());
+
+        flusher.release(flush);
+        let Poll::Ready(buffer) = acquire.as_mut().poll(&mut cx) else {
+            panic!("a released buffer is not acquired");
+        };
+        assert_eq!(buffer, [1; 8]);
+
+        let flush = block_on(flusher.receive());
+        assert_eq!((flush.area(), flush.data()), (second_area, &[2; 2][..]));
+        flusher.release(flush);
+    }
+
+    #[test]
+    fn wait_idle_waits_until_the_submitted_buffers_are_sent() {
+        let (mut double_buffer, mut flusher) = double_buffer(8);
+        let mut cx = Context::from_waker(Waker::noop());
+        let area = Rectangle::new(Point::zero(), Size::new(2, 2));
+
+        let buffer = block_on(double_buffer.acquire());
+        block_on(double_buffer.submit(buffer, area));
+
+        {
+            let mut wait_idle = pin!(double_buffer.wait_idle());
+            assert!(wait_idle.as_mut().poll(&mut cx).is_pending());
+
+            let flush = block_on(flusher.receive());
+            flusher.release(flush);
+            assert!(wait_idle.as_mut().poll(&mut cx).is_ready());
+        }
+
+        // Both buffers are free again
+        let first = block_on(double_buffer.acquire());
+        let second = block_on(double_buffer.acquire());
+        assert_eq!(double_buffer.buffer_pixels(), 4);
+        double_buffer.release(first);
+        double_buffer.release(second);
+    }
+}
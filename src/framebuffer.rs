@@ -4,18 +4,22 @@ with some minor changes.
 */
 
 use embedded_graphics_core::{
-    pixelcolor::raw::ToBytes,
-    prelude::{DrawTarget, OriginDimensions, PixelColor, Size},
+    prelude::{DrawTarget, OriginDimensions, Size},
     Pixel,
 };
 use log::*;
 
+use crate::pixel_format::InterfaceColor;
+
 /// Memory buffer that can serve as a [`DrawTarget`].
 ///
 /// Compared to the one in `embedded-graphics`, this one allows to use the
 /// same slice of bytes to draw display areas of different shape or color
 /// depth, sequentially. Because of the dynamic shape, it's likely a bit
 /// slower.
+///
+/// The pixels are stored in the byte layout they are sent to the display in,
+/// see [`InterfaceColor`].
 pub struct Framebuffer<'a, C> {
     size: Size,
     data: &'a mut [u8],
@@ -24,9 +28,9 @@ pub struct Framebuffer<'a, C> {
 
 impl<'a, C> Framebuffer<'a, C>
 where
-    C: ToBytes,
+    C: InterfaceColor,
 {
-    const BYTES_PER_PIXEL: usize = core::mem::size_of::<C::Bytes>();
+    const BYTES_PER_PIXEL: usize = C::BYTES_PER_PIXEL;
 
     /// Creates a framebuffer.
     ///
@@ -45,6 +49,11 @@ where
         s
     }
 
+    /// Give back the data slice, e.g. to hand it to the task sending it to the display.
+    pub fn release(self) -> &'a mut [u8] {
+        self.data
+    }
+
     pub fn data(&self) -> &[u8] {
         &self.data[..self.pixel_count() * Self::BYTES_PER_PIXEL]
     }
@@ -62,8 +71,7 @@ impl<'a, C> OriginDimensions for Framebuffer<'a, C> {
 
 impl<'a, C> DrawTarget for Framebuffer<'a, C>
 where
-    C: PixelColor + ToBytes,
-    C::Bytes: AsRef<[u8]>,
+    C: InterfaceColor,
 {
     type Color = C;
     type Error = core::convert::Infallible;
@@ -92,7 +100,7 @@ where
 
             // Copy pixel to framebuffer memory
             self.data[offset..offset + Self::BYTES_PER_PIXEL]
-                .copy_from_slice(p.1.to_be_bytes().as_ref());
+                .copy_from_slice(p.1.to_interface_bytes().as_ref());
         }
         Ok(())
     }
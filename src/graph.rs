@@ -0,0 +1,465 @@
+use core::fmt::Write;
+use heapless::{Deque, String};
+
+use embedded_graphics::{
+    mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::Rectangle,
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+};
+
+use crate::double_buffer::DoubleBuffer;
+use crate::framebuffer::Framebuffer;
+use crate::sensor::{EnvironmentReading, TemperatureUnit};
+
+/// Width of the label columns left (temperature) and right (humidity) of the plot,
+/// using font 6x10 a label is 4 characters 4x6=24 wide
+const LABEL_WIDTH: u32 = 24;
+
+/// Number of horizontal grid divisions
+const GRID_ROWS: i32 = 4;
+
+/// Distance between the vertical grid lines in pixels
+const GRID_COLUMN_SPACING: i32 = 34;
+
+/// Smallest range shown on the Y axis in tenths, so noise does not fill the plot
+const MIN_SCALE_RANGE: i16 = 20;
+
+const BACKGROUND_COLOR: Rgb565 = Rgb565::BLACK;
+const GRID_COLOR: Rgb565 = Rgb565::CSS_DARK_SLATE_GRAY;
+const TEMPERATURE_COLOR: Rgb565 = Rgb565::RED;
+const HUMIDITY_LINE_COLOR: Rgb565 = Rgb565::CSS_DODGER_BLUE;
+const HUMIDITY_AREA_COLOR: Rgb565 = Rgb565::CSS_NAVY;
+
+/// Range of the Y axis in tenths
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Scale {
+    min: i16,
+    max: i16,
+}
+
+impl Scale {
+    /// Scale that fits all values, rounded outwards to whole units
+    fn fit<I>(values: I) -> Option<Self>
+    where
+        I: Iterator<Item = i16>,
+    {
+        let (min, max) = values.fold(None, |range, v| match range {
+            None => Some((v, v)),
+            Some((min, max)) => Some((v.min(min), v.max(max))),
+        })?;
+
+        let mut min = min.div_euclid(10) * 10;
+        let mut max = (max + 9).div_euclid(10) * 10;
+        if max - min < MIN_SCALE_RANGE {
+            let grow = MIN_SCALE_RANGE - (max - min);
+            min -= grow / 2;
+            max += grow - grow / 2;
+        }
+
+        Some(Self { min, max })
+    }
+
+    /// Row of the value in a plot `height` rows high, row 0 is the top
+    fn row(&self, value: i16, height: u32) -> i16 {
+        let bottom = height as i32 - 1;
+        let offset = (value - self.min) as i32 * bottom / (self.max - self.min) as i32;
+        (bottom - offset).clamp(0, bottom) as i16
+    }
+}
+
+/// How the history of the readings is shown
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HistoryView {
+    /// Scrolling graph of the temperature and humidity
+    Graph,
+    /// Scrolling log of the readings, one line per reading, using hardware scrolling
+    Log,
+}
+
+/// What was drawn in a column of the plot
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Column {
+    /// First and last row of the temperature line
+    temperature: Option<(i16, i16)>,
+    /// Top row of the humidity area
+    humidity: Option<i16>,
+}
+
+impl Column {
+    /// Column without samples, only the grid
+    const EMPTY: Self = Self {
+        temperature: None,
+        humidity: None,
+    };
+}
+
+/// Scrolling history graph of the last `N` temperature and humidity samples.
+///
+/// Every sample is one column of the plot, the newest sample is on the right.
+/// The temperature is drawn as a line and the humidity as an area, each with its
+/// own automatically scaled Y axis labeled on the left and right of the plot.
+///
+/// The graph is drawn in horizontal strips through a [`Framebuffer`] so it can
+/// be taller than the pixel buffer. Only the runs of columns that differ from the
+/// last render are sent to the display, e.g. a steady temperature only changes the
+/// column where the line starts. The labels are only sent when a scale changed.
+pub struct HistoryGraph<const N: usize> {
+    /// Top left corner of the graph on the display.
+    top_left: Point,
+    /// Height of the graph in pixels.
+    height: u32,
+    /// Unit of the temperature labels.
+    unit: TemperatureUnit,
+    /// Temperature samples in tenths of a degree celsius, oldest first.
+    temperature: Deque<i16, N>,
+    /// Humidity samples in tenths of a percent, oldest first.
+    humidity: Deque<Option<i16>, N>,
+    /// Scales used for the last render.
+    drawn_scales: Option<(Scale, Option<Scale>)>,
+    /// Columns drawn by the last render.
+    drawn: [Column; N],
+    /// Whether a sample was added since the last render.
+    dirty: bool,
+}
+
+impl<const N: usize> HistoryGraph<N> {
+    /// Creates an empty graph, the graph is `N` plus the label columns wide.
+    pub const fn new(top_left: Point, height: u32, unit: TemperatureUnit) -> Self {
+        Self {
+            top_left,
+            height,
+            unit,
+            temperature: Deque::new(),
+            humidity: Deque::new(),
+            drawn_scales: None,
+            drawn: [Column::EMPTY; N],
+            dirty: false,
+        }
+    }
+
+    /// Area of the display covered by the graph
+    pub fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(
+            self.top_left,
+            Size::new(N as u32 + 2 * LABEL_WIDTH, self.height),
+        )
+    }
+
+    /// Add a sample, dropping the oldest one when the graph is full
+    pub fn push(&mut self, reading: &EnvironmentReading) {
+        if self.temperature.is_full() {
+            self.temperature.pop_front();
+            self.humidity.pop_front();
+        }
+
+        let _ = self.temperature.push_back(reading.temp_tenths_celsius);
+        let _ = self
+            .humidity
+            .push_back(reading.humidity_tenths.map(|h| h as i16));
+        self.dirty = true;
+    }
+
+    /// Draw the graph on the display if a sample was added since the last render.
+    ///
+    /// # Parameters
+    ///
+    /// - `double_buffer`: Pixel buffers the strips are drawn in and sent to the display.
+    ///
+    /// # Panics
+    ///
+    /// If a buffer cannot hold a single row of the graph.
+    pub async fn render(&mut self, double_buffer: &mut DoubleBuffer) {
+        if !self.dirty {
+            return;
+        }
+
+        let Some(temperature_scale) = Scale::fit(self.temperature.iter().copied()) else {
+            return;
+        };
+        let humidity_scale = Scale::fit(self.humidity.iter().flatten().copied());
+        let scales = (temperature_scale, humidity_scale);
+
+        let columns = self.columns(&scales);
+
+        // A new scale moves every column and changes the labels
+        if self.drawn_scales != Some(scales) {
+            let x1 = N as u32 + 2 * LABEL_WIDTH;
+            self.render_columns(double_buffer, &columns, &scales, 0, x1)
+                .await;
+        } else {
+            let mut x = 0;
+            while x < N {
+                if columns[x] == self.drawn[x] {
+                    x += 1;
+                    continue;
+                }
+
+                let first = x;
+                while x < N && columns[x] != self.drawn[x] {
+                    x += 1;
+                }
+                let (x0, x1) = (LABEL_WIDTH + first as u32, LABEL_WIDTH + x as u32);
+                self.render_columns(double_buffer, &columns, &scales, x0, x1)
+                    .await;
+            }
+        }
+
+        self.drawn = columns;
+        self.drawn_scales = Some(scales);
+        self.dirty = false;
+    }
+
+    /// Draw the columns `x0..x1` of the graph in strips and send them to the display,
+    /// the labels are drawn when the columns include the label columns.
+    async fn render_columns(
+        &self,
+        double_buffer: &mut DoubleBuffer,
+        columns: &[Column; N],
+        scales: &(Scale, Option<Scale>),
+        x0: u32,
+        x1: u32,
+    ) {
+        let with_labels = x0 < LABEL_WIDTH;
+        let width = x1 - x0;
+        let strip_height = (double_buffer.buffer_pixels() as u32 / width).min(self.height);
+
+        let mut y0 = 0;
+        while y0 < self.height {
+            let rows = strip_height.min(self.height - y0);
+            let buffer = double_buffer.acquire().await;
+            let mut fb = Framebuffer::<Rgb565>::new(buffer, Size::new(width, rows));
+
+            let strip = Rectangle::new(Point::new(x0 as i32, y0 as i32), Size::new(width, rows));
+            let bounds = fb.bounding_box();
+            let colors = strip.points().map(|p| self.color_at(columns, p));
+            // Drawing in a framebuffer cannot fail
+            let _ = fb.fill_contiguous(&bounds, colors);
+
+            if with_labels {
+                // Labels are drawn in graph coordinates and may cross the strip edges
+                let mut clipped = fb.clipped(&bounds);
+                let mut target = clipped.translated(Point::new(-(x0 as i32), -(y0 as i32)));
+                self.draw_labels(&mut target, scales);
+            }
+
+            // The next strip is drawn in the other buffer while this one is sent
+            let area = Rectangle::new(self.top_left + strip.top_left, strip.size);
+            double_buffer.submit(fb.release(), area).await;
+
+            y0 += rows;
+        }
+    }
+
+    /// Compute what each column of the plot shows with the given scales
+    fn columns(&self, scales: &(Scale, Option<Scale>)) -> [Column; N] {
+        let mut columns = [Column::EMPTY; N];
+        let first = N - self.temperature.len();
+        let mut previous_row = None;
+
+        for (i, (t, h)) in self
+            .temperature
+            .iter()
+            .zip(self.humidity.iter())
+            .enumerate()
+        {
+            let row = scales.0.row(*t, self.height);
+
+            // Join the line to the previous sample so steps are not drawn as gaps
+            let span = match previous_row {
+                Some(previous) => (row.min(previous), row.max(previous)),
+                None => (row, row),
+            };
+            previous_row = Some(row);
+
+            columns[first + i] = Column {
+                temperature: Some(span),
+                humidity: h.zip(scales.1).map(|(h, s)| s.row(h, self.height)),
+            };
+        }
+
+        columns
+    }
+
+    /// Color of the pixel at `point` in graph coordinates
+    fn color_at(&self, columns: &[Column; N], point: Point) -> Rgb565 {
+        let x = point.x - LABEL_WIDTH as i32;
+        if x < 0 || x >= N as i32 {
+            return BACKGROUND_COLOR;
+        }
+
+        let row = point.y as i16;
+        let column = &columns[x as usize];
+
+        if let Some((top, bottom)) = column.temperature {
+            if (top..=bottom).contains(&row) {
+                return TEMPERATURE_COLOR;
+            }
+        }
+
+        if let Some(top) = column.humidity {
+            if row == top {
+                return HUMIDITY_LINE_COLOR;
+            }
+            if row > top {
+                return HUMIDITY_AREA_COLOR;
+            }
+        }
+
+        // Graphs lower than the number of grid rows have a grid line on every row
+        let grid_spacing = ((self.height as i32 - 1) / GRID_ROWS).max(1);
+        if point.y % grid_spacing == 0 || x % GRID_COLUMN_SPACING == 0 {
+            return GRID_COLOR;
+        }
+
+        BACKGROUND_COLOR
+    }
+
+    /// Draw the maximum and minimum of each scale next to the plot
+    fn draw_labels<T>(&self, target: &mut T, scales: &(Scale, Option<Scale>))
+    where
+        T: DrawTarget<Color = Rgb565>,
+    {
+        let temperature_style = MonoTextStyle::new(&FONT_6X10, TEMPERATURE_COLOR);
+        let humidity_style = MonoTextStyle::new(&FONT_6X10, HUMIDITY_LINE_COLOR);
+        let right_edge = (N as u32 + 2 * LABEL_WIDTH) as i32 - 1;
+        let bottom = self.height as i32 - 1;
+
+        let mut draw = |value: &str, x: i32, y: i32, style, alignment, baseline| {
+            let text_style = TextStyleBuilder::new()
+                .alignment(alignment)
+                .baseline(baseline)
+                .build();
+            let _ = Text::with_text_style(value, Point::new(x, y), style, text_style).draw(target);
+        };
+
+        for (tenths, y, baseline) in [
+            (scales.0.max, 0, Baseline::Top),
+            (scales.0.min, bottom, Baseline::Bottom),
+        ] {
+            let mut label = String::<8>::new();
+            let value = self.unit.convert_celsius(tenths as f32 / 10.0);
+            let _ = write!(label, "{:.0}{}", value, self.unit.symbol());
+            draw(&label, 0, y, temperature_style, Alignment::Left, baseline);
+        }
+
+        if let Some(scale) = scales.1 {
+            for (tenths, y, baseline) in [
+                (scale.max, 0, Baseline::Top),
+                (scale.min, bottom, Baseline::Bottom),
+            ] {
+                let mut label = String::<8>::new();
+                let _ = write!(label, "{}%", tenths / 10);
+                draw(
+                    &label,
+                    right_edge,
+                    y,
+                    humidity_style,
+                    Alignment::Right,
+                    baseline,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::test_util::{block_on_flushing, double_buffer};
+
+    fn reading(temp_tenths_celsius: i16) -> EnvironmentReading {
+        EnvironmentReading {
+            temp_tenths_celsius,
+            humidity_tenths: Some(450),
+            pressure_pa: None,
+        }
+    }
+
+    #[test]
+    fn scale_fits_the_values_rounded_outwards() {
+        let fit = |values: &[i16]| Scale::fit(values.iter().copied());
+        let scale = |min, max| Some(Scale { min, max });
+
+        assert_eq!(fit(&[]), None);
+        assert_eq!(fit(&[215, 231]), scale(210, 240));
+        assert_eq!(fit(&[-55, -41]), scale(-60, -40));
+        assert_eq!(fit(&[-5, 5]), scale(-10, 10));
+
+        // Ranges below the minimum grow evenly on both sides
+        assert_eq!(fit(&[215]), scale(205, 225));
+        assert_eq!(fit(&[-20]), scale(-30, -10));
+    }
+
+    #[test]
+    fn scale_row_counts_from_the_top_and_clamps() {
+        let scale = Scale { min: 200, max: 220 };
+        assert_eq!(scale.row(220, 37), 0);
+        assert_eq!(scale.row(210, 37), 18);
+        assert_eq!(scale.row(200, 37), 36);
+        assert_eq!(scale.row(230, 37), 0);
+        assert_eq!(scale.row(190, 37), 36);
+
+        let negative = Scale { min: -60, max: -40 };
+        assert_eq!(negative.row(-50, 11), 5);
+        assert_eq!(negative.row(-60, 11), 10);
+    }
+
+    #[test]
+    fn columns_join_the_steps_of_the_line() {
+        let mut graph = HistoryGraph::<4>::new(Point::zero(), 11, TemperatureUnit::Celsius);
+        for temperature in [200, 220, 220, 200] {
+            graph.push(&reading(temperature));
+        }
+        let scales = (Scale { min: 200, max: 220 }, None);
+
+        let lines = graph.columns(&scales).map(|column| column.temperature);
+        assert_eq!(
+            lines,
+            [Some((10, 10)), Some((0, 10)), Some((0, 0)), Some((0, 10))]
+        );
+
+        // Fewer samples than columns are drawn on the right
+        let mut graph = HistoryGraph::<4>::new(Point::zero(), 11, TemperatureUnit::Celsius);
+        graph.push(&reading(210));
+        let lines = graph.columns(&scales).map(|column| column.temperature);
+        assert_eq!(lines, [None, None, None, Some((5, 5))]);
+    }
+
+    /// Areas of the display sent by a render
+    fn render<const N: usize>(graph: &mut HistoryGraph<N>) -> Vec<Rectangle> {
+        let (mut double_buffer, mut flusher) = double_buffer(2048);
+        let mut areas = Vec::new();
+        block_on_flushing(graph.render(&mut double_buffer), &mut flusher, |flush| {
+            areas.push(flush.area())
+        });
+        areas
+    }
+
+    #[test]
+    fn render_only_sends_the_changed_columns() {
+        let top_left = Point::new(0, 100);
+        let mut graph = HistoryGraph::<8>::new(top_left, 10, TemperatureUnit::Celsius);
+        assert_eq!(render(&mut graph), []);
+
+        // The first render draws the whole graph with the labels
+        for _ in 0..5 {
+            graph.push(&reading(215));
+        }
+        let areas = render(&mut graph);
+        assert_eq!(areas, [Rectangle::new(top_left, Size::new(56, 10))]);
+
+        // A steady temperature only adds a column on the left of the line
+        graph.push(&reading(215));
+        let column = |x, width| Rectangle::new(Point::new(24 + x, 100), Size::new(width, 10));
+        assert_eq!(render(&mut graph), [column(2, 1)]);
+        assert_eq!(render(&mut graph), []);
+
+        // A step changes the newest column and the one before it keeps its line
+        graph.push(&reading(220));
+        assert_eq!(render(&mut graph), [column(1, 1), column(7, 1)]);
+    }
+}
@@ -2,59 +2,395 @@ use log::*;
 
 use embedded_hal::digital::OutputPin;
 use embedded_hal_async::delay::DelayNs;
-use embedded_hal_async::spi::SpiDevice;
+use embedded_hal_async::spi::{Operation, SpiDevice};
 
 use embedded_graphics_core::{
+    draw_target::DrawTarget,
     geometry::Dimensions,
-    pixelcolor::raw::ToBytes,
-    prelude::{OriginDimensions, PixelColor, Point, Size},
-    primitives::Rectangle,
+    pixelcolor::{Rgb565, Rgb666},
+    prelude::{OriginDimensions, Point, Size},
+    primitives::{PointsIter, Rectangle},
+    Pixel,
 };
 
+use crate::backlight::{self, Backlight, MAX_BRIGHTNESS};
 use crate::command;
 use crate::framebuffer::Framebuffer;
+use crate::init_table::{GammaCurve, InitCommand, VENDOR_INIT};
+use crate::pixel_format::{InterfaceColor, MAX_BYTES_PER_PIXEL};
+
+/// Number of pixels sent per SPI write by the [`DrawTarget`] implementation, the
+/// pixels are collected in a buffer on the stack
+const PIXEL_CHUNK_SIZE: usize = 256;
 
 /// Specify state of specific mode of operation
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ModeState {
     On,
     Off,
 }
 
+/// Number of pixels read per SPI transaction by [`Ili9341::read_pixels`], the display
+/// sends 3 bytes per pixel which are collected in a buffer on the stack
+const READ_PIXEL_CHUNK_SIZE: usize = 64;
+
+/// Default number of bytes sent per SPI write, the size of the DMA buffer used on the M5Stack
+const DEFAULT_CHUNK_SIZE: usize = 4096;
+
+/// Memory Access Control bit for the row address order, mirrors the rows
+const MADCTL_MY: u8 = 0x80;
+
+/// Memory Access Control bit for the column address order, mirrors the columns
+const MADCTL_MX: u8 = 0x40;
+
+/// Memory Access Control bit for the row / column exchange, swaps the axes
+const MADCTL_MV: u8 = 0x20;
+
+/// Memory Access Control bit for the BGR color order
+const MADCTL_BGR: u8 = 0x08;
+
+/// Clockwise rotation of the display from the native orientation of the panel.
+///
+/// # Variants
+///
+/// - Deg0: Native orientation, landscape on the M5Stack
+/// - Deg90
+/// - Deg180
+/// - Deg270
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rotation {
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+/// Order of the red and blue subpixels of the panel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorOrder {
+    Rgb,
+    Bgr,
+}
+
 /// Display orientation.
 ///
-/// This enum represents the different possible orientations supported in this display
+/// The display is rotated first, the mirroring is in the rotated display
+/// coordinates, e.g. `mirror_x` always flips the display left to right.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Orientation {
+    /// Rotation from the native orientation of the panel.
+    pub rotation: Rotation,
+    /// Flip the display left to right.
+    pub mirror_x: bool,
+    /// Flip the display top to bottom.
+    pub mirror_y: bool,
+    /// Order of the red and blue subpixels.
+    pub color_order: ColorOrder,
+}
+
+impl Default for Orientation {
+    fn default() -> Self {
+        Self::new(Rotation::Deg0)
+    }
+}
+
+impl Orientation {
+    /// Creates an orientation with the rotation, not mirrored and in BGR order.
+    pub const fn new(rotation: Rotation) -> Self {
+        Self {
+            rotation,
+            mirror_x: false,
+            mirror_y: false,
+            color_order: ColorOrder::Bgr,
+        }
+    }
+
+    /// Whether the rows and columns of the panel are swapped, i.e. rotated by 90 or 270 degrees
+    pub const fn swaps_axes(&self) -> bool {
+        matches!(self.rotation, Rotation::Deg90 | Rotation::Deg270)
+    }
+
+    /// Size of the display in this orientation, for a panel of `panel_size` in the native orientation
+    pub const fn size(&self, panel_size: Size) -> Size {
+        if self.swaps_axes() {
+            Size::new(panel_size.height, panel_size.width)
+        } else {
+            panel_size
+        }
+    }
+
+    /// Parameter of the Memory Access Control command for this orientation
+    pub const fn madctl(&self) -> u8 {
+        let mut madctl = match self.rotation {
+            Rotation::Deg0 => 0,
+            Rotation::Deg90 => MADCTL_MV | MADCTL_MX,
+            Rotation::Deg180 => MADCTL_MX | MADCTL_MY,
+            Rotation::Deg270 => MADCTL_MV | MADCTL_MY,
+        };
+
+        // With the axes swapped the columns of the display are the rows of the panel
+        let (mirror_columns, mirror_rows) = if self.swaps_axes() {
+            (self.mirror_y, self.mirror_x)
+        } else {
+            (self.mirror_x, self.mirror_y)
+        };
+        if mirror_columns {
+            madctl ^= MADCTL_MX;
+        }
+        if mirror_rows {
+            madctl ^= MADCTL_MY;
+        }
+
+        if let ColorOrder::Bgr = self.color_order {
+            madctl |= MADCTL_BGR;
+        }
+
+        madctl
+    }
+}
+
+/// Content adaptive brightness control of the LEDPWM output.
+///
+/// # Variants
+///
+/// - Off
+/// - UserInterface: Dims slightly, for mostly static user interfaces
+/// - StillPicture: Dims more, for photos
+/// - MovingImage: Dims the most, for video
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AdaptiveBrightness {
+    Off = 0x00,
+    UserInterface = 0x01,
+    StillPicture = 0x02,
+    MovingImage = 0x03,
+}
+
+/// Commands sent by [`Ili9341::initialize`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InitSequence {
+    /// Hardware and software reset, then the orientation, pixel format and inversion,
+    /// sleep out and display on
+    Minimal,
+    /// No reset, only the orientation, pixel format and inversion, sleep out and
+    /// display on, for a display already initialized e.g. by the bootloader
+    Reconfigure,
+    /// Reset, the power, timing and gamma settings recommended for the ILI9341 and
+    /// then as `Minimal`
+    Vendor,
+    /// Reset, the commands of the table and then as `Minimal`
+    Custom(&'static [InitCommand]),
+}
+
+/// What [`Ili9341::write_pixels`] does with an area that is not completely inside the display.
 ///
 /// # Variants
 ///
-/// - Portrait
-/// - Landscape
-#[allow(unused)]
-#[derive(Clone, Copy, PartialEq)]
-pub enum Orientation {
-    Potrait,
-    Landscape,
+/// - Error: Nothing is sent and `Error::OutOfBounds` is returned
+/// - Clip: Only the pixels inside the display are sent, the others are dropped
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClipMode {
+    Error,
+    Clip,
 }
 
 /// Optional configuration structure to invert the color and screen orientation
+///
+/// Use [`Config::builder`] to change the defaults of the M5Stack display.
 pub struct Config {
     inverted_color: ModeState,
     orientation: Orientation,
+    /// Height of the panel in the native orientation
     height: usize,
+    /// Width of the panel in the native orientation
     width: usize,
+    /// Number of columns of the display memory in the native orientation
+    memory_columns: u16,
+    /// Number of rows of the display memory in the native orientation
+    memory_rows: u16,
+    /// Offset of the first visible column in the display memory
+    column_offset: u16,
+    /// Offset of the first visible row in the display memory
+    row_offset: u16,
+    /// Maximum number of bytes sent per SPI write
+    chunk_size: usize,
+    /// Commands sent to initialize the display
+    init_sequence: InitSequence,
+    /// Gamma curves sent after the init sequence, `None` keeps the curves of the sequence
+    gamma: Option<GammaCurve>,
+    /// What to do with pixels written outside of the display
+    clip_mode: ClipMode,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             inverted_color: ModeState::On,
-            orientation: Orientation::Landscape,
+            orientation: Orientation::default(),
             height: 240,
             width: 320,
+            memory_columns: 320,
+            memory_rows: 240,
+            column_offset: 0,
+            row_offset: 0,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            init_sequence: InitSequence::Minimal,
+            gamma: None,
+            clip_mode: ClipMode::Error,
+        }
+    }
+}
+
+impl Config {
+    /// Creates a builder starting from the default configuration
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder {
+            config: Self::default(),
+        }
+    }
+}
+
+/// Builder for [`Config`], the configuration is validated by [`ConfigBuilder::build`].
+///
+/// ```ignore
+/// let config = Config::builder()
+///     .panel_size(240, 320)
+///     .memory_size(240, 320)
+///     .rotation(Rotation::Deg90)
+///     .color_order(ColorOrder::Rgb)
+///     .build()?;
+/// ```
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    /// Invert the colors, the M5Stack panel needs inverted colors
+    pub fn inverted_color(mut self, inverted_color: ModeState) -> Self {
+        self.config.inverted_color = inverted_color;
+        self
+    }
+
+    /// Set the rotation, mirroring and color order at once
+    pub fn orientation(mut self, orientation: Orientation) -> Self {
+        self.config.orientation = orientation;
+        self
+    }
+
+    /// Rotate the display from the native orientation of the panel
+    pub fn rotation(mut self, rotation: Rotation) -> Self {
+        self.config.orientation.rotation = rotation;
+        self
+    }
+
+    /// Flip the display left to right and / or top to bottom, after the rotation
+    pub fn mirror(mut self, mirror_x: bool, mirror_y: bool) -> Self {
+        self.config.orientation.mirror_x = mirror_x;
+        self.config.orientation.mirror_y = mirror_y;
+        self
+    }
+
+    /// Order of the red and blue subpixels of the panel
+    pub fn color_order(mut self, color_order: ColorOrder) -> Self {
+        self.config.orientation.color_order = color_order;
+        self
+    }
+
+    /// Size of the panel in pixels, in the native orientation of the panel
+    pub fn panel_size(mut self, width: u16, height: u16) -> Self {
+        self.config.width = width as usize;
+        self.config.height = height as usize;
+        self
+    }
+
+    /// Size of the display memory of the controller in the native orientation.
+    ///
+    /// The default of 320x240 is the memory of the ILI9342C of the M5Stack, a bare
+    /// ILI9341 has a memory of `.memory_size(240, 320)`.
+    pub fn memory_size(mut self, columns: u16, rows: u16) -> Self {
+        self.config.memory_columns = columns;
+        self.config.memory_rows = rows;
+        self
+    }
+
+    /// Offset of the visible pixels in the display memory in the native orientation,
+    /// for panels smaller than the memory of the controller, e.g. ST7789 panels.
+    ///
+    /// The driver moves the offset to the other side of the memory when the display
+    /// is rotated or mirrored.
+    pub fn offset(mut self, column_offset: u16, row_offset: u16) -> Self {
+        self.config.column_offset = column_offset;
+        self.config.row_offset = row_offset;
+        self
+    }
+
+    /// Maximum number of bytes sent per SPI write, e.g. the size of the DMA buffer.
+    ///
+    /// The driver rounds it down to whole pixels of its color format.
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.config.chunk_size = chunk_size;
+        self
+    }
+
+    /// Commands sent to initialize the display
+    pub fn init_sequence(mut self, init_sequence: InitSequence) -> Self {
+        self.config.init_sequence = init_sequence;
+        self
+    }
+
+    /// Gamma curves sent after the init sequence, replacing the vendor curves
+    pub fn gamma(mut self, gamma: GammaCurve) -> Self {
+        self.config.gamma = Some(gamma);
+        self
+    }
+
+    /// What to do with pixels written outside of the display, an error by default
+    pub fn clip_mode(mut self, clip_mode: ClipMode) -> Self {
+        self.config.clip_mode = clip_mode;
+        self
+    }
+
+    /// Validate the configuration.
+    ///
+    /// # Errors
+    ///
+    /// - `ConfigError::InvalidPanelSize` if the width or height is 0.
+    /// - `ConfigError::OutOfMemory` if the panel plus its offset does not fit in the display memory.
+    /// - `ConfigError::InvalidChunkSize` if the chunk size is not a whole number of pixels.
+    pub fn build(self) -> Result<Config, ConfigError> {
+        let config = self.config;
+
+        if config.width == 0 || config.height == 0 {
+            return Err(ConfigError::InvalidPanelSize);
         }
+
+        if config.width + config.column_offset as usize > config.memory_columns as usize
+            || config.height + config.row_offset as usize > config.memory_rows as usize
+        {
+            return Err(ConfigError::OutOfMemory);
+        }
+
+        // A chunk holds a pixel of every color format, and whole pixels of Rgb565
+        if config.chunk_size < MAX_BYTES_PER_PIXEL || config.chunk_size % 2 == 1 {
+            return Err(ConfigError::InvalidChunkSize);
+        }
+
+        Ok(config)
     }
 }
 
+/// Errors returned by [`ConfigBuilder::build`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfigError {
+    /// The width or height of the panel is 0
+    InvalidPanelSize,
+
+    /// The panel plus its offset does not fit in the display memory
+    OutOfMemory,
+
+    /// The chunk size is smaller than a pixel or not a whole number of Rgb565 pixels
+    InvalidChunkSize,
+}
+
 /// Ili9341 async display driver.
 ///
 /// This struct provides an interface for controlling the Ili9341 display
@@ -65,18 +401,22 @@ impl Default for Config {
 /// - `SPI`: The SPI device used for communication with the display.
 /// - `DC`: The data/command pin, used to switch between sending data and commands.
 /// - `RST`: The reset pin, used to reset the display.
-/// - `PO`: The power on pin, used to power on the display.
+/// - `PO`: The backlight of the display.
+/// - `C`: The color format of the pixels sent to the display, `Rgb565` by default.
 ///
 /// # Constraints
 ///
 /// - `SPI`: Must implement the `SpiDevice` trait.
-/// - `DC`, `RST`, `PO`: Must implement the `OutputPin` trait with `Error = Infallible`.
-pub struct Ili9341<SPI, DC, RST, PO>
+/// - `DC`, `RST`: Must implement the `OutputPin` trait with `Error = Infallible`.
+/// - `PO`: Must implement the `Backlight` trait, e.g. a `PinBacklight` for an on/off pin.
+/// - `C`: Must implement the `InterfaceColor` trait, e.g. `Rgb666` for 18 bits per pixel.
+pub struct Ili9341<SPI, DC, RST, PO, C = Rgb565>
 where
     SPI: SpiDevice,
     DC: OutputPin,
     RST: OutputPin,
-    PO: OutputPin,
+    PO: Backlight,
+    C: InterfaceColor,
 {
     /// SPI device used for communication with the display.
     spi: SPI,
@@ -84,24 +424,57 @@ where
     dc: DC,
     /// Reset pin, used to reset the display.
     rst: RST,
-    /// Power on pin, used to power on the display.
+    /// Backlight of the display.
     power: PO,
     /// Whether the colors are inverted (`true`) or not (`false`).
     inverted: ModeState,
     /// Orientation of the display.
     orientation: Orientation,
-    /// Height of display
+    /// Size of the panel in the native orientation.
+    panel_size: Size,
+    /// Size of the display memory in the native orientation.
+    memory_size: Size,
+    /// Height of display in the current orientation
     pub height: usize,
-    /// Width of display
+    /// Width of display in the current orientation
     pub width: usize,
+    /// First row of the vertical scrolling area.
+    scroll_top: u16,
+    /// Number of rows in the vertical scrolling area.
+    scroll_height: u16,
+    /// Offset of the first visible column in the display memory, in the native orientation.
+    column_offset: u16,
+    /// Offset of the first visible row in the display memory, in the native orientation.
+    row_offset: u16,
+    /// Offset added to the column addresses sent in the current orientation.
+    x_offset: u16,
+    /// Offset added to the row addresses sent in the current orientation.
+    y_offset: u16,
+    /// Maximum number of bytes sent per SPI write.
+    chunk_size: usize,
+    /// Commands sent to initialize the display.
+    init_sequence: InitSequence,
+    /// Gamma curves sent after the init sequence.
+    gamma: Option<GammaCurve>,
+    /// What to do with pixels written outside of the display.
+    clip_mode: ClipMode,
+    /// Whether the display is sleeping, pixels cannot be written while sleeping.
+    sleep_mode: ModeState,
+    /// Whether the display shows 8 colors to save power.
+    idle_mode: ModeState,
+    /// Whether the display shows the display memory.
+    display_mode: ModeState,
+    /// Color format of the pixels.
+    _color: core::marker::PhantomData<C>,
 }
 
-impl<SPI, DC, RST, PO> OriginDimensions for Ili9341<SPI, DC, RST, PO>
+impl<SPI, DC, RST, PO, C> OriginDimensions for Ili9341<SPI, DC, RST, PO, C>
 where
     SPI: SpiDevice,
     DC: OutputPin,
     RST: OutputPin,
-    PO: OutputPin,
+    PO: Backlight,
+    C: InterfaceColor,
 {
     fn size(&self) -> Size {
         Size::new(self.width as u32, self.height as u32)
@@ -113,22 +486,89 @@ where
     SPI: SpiDevice,
     DC: OutputPin,
     RST: OutputPin,
-    PO: OutputPin,
+    PO: Backlight,
 {
-    /// Creates a new driver instance that uses hardware SPI.
+    /// Creates a new driver instance that uses hardware SPI, sending `Rgb565` pixels.
     pub fn new(spi_device: SPI, dc: DC, rst: RST, power: PO, config: Config) -> Self {
-        Self {
+        Self::with_color_format(spi_device, dc, rst, power, config)
+    }
+}
+
+impl<SPI, DC, RST, PO, C> Ili9341<SPI, DC, RST, PO, C>
+where
+    SPI: SpiDevice,
+    DC: OutputPin,
+    RST: OutputPin,
+    PO: Backlight,
+    C: InterfaceColor,
+{
+    /// Creates a new driver instance that uses hardware SPI, sending pixels in the color format `C`.
+    ///
+    /// ```ignore
+    /// let display: Ili9341<_, _, _, _, Rgb666> =
+    ///     Ili9341::with_color_format(spi_device, dc, rst, backlight, config);
+    /// ```
+    pub fn with_color_format(spi_device: SPI, dc: DC, rst: RST, power: PO, config: Config) -> Self {
+        let panel_size = Size::new(config.width as u32, config.height as u32);
+        let size = config.orientation.size(panel_size);
+
+        let mut display = Self {
             spi: spi_device,
             dc,
             rst,
             power,
             inverted: config.inverted_color,
             orientation: config.orientation,
-            height: config.height,
-            width: config.width,
+            panel_size,
+            memory_size: Size::new(config.memory_columns as u32, config.memory_rows as u32),
+            height: size.height as usize,
+            width: size.width as usize,
+            scroll_top: 0,
+            scroll_height: config.height as u16,
+            column_offset: config.column_offset,
+            row_offset: config.row_offset,
+            x_offset: 0,
+            y_offset: 0,
+            chunk_size: config.chunk_size / C::BYTES_PER_PIXEL * C::BYTES_PER_PIXEL,
+            init_sequence: config.init_sequence,
+            gamma: config.gamma,
+            clip_mode: config.clip_mode,
+            // The display sleeps with the display off after a reset
+            sleep_mode: ModeState::On,
+            idle_mode: ModeState::Off,
+            display_mode: ModeState::Off,
+            _color: core::marker::PhantomData,
+        };
+        (display.x_offset, display.y_offset) = display.window_offset(config.orientation);
+
+        display
+    }
+
+    /// Current orientation of the display
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// Rows of the display memory in the current orientation, the rows the vertical
+    /// scrolling area is defined in, see [`Self::define_scroll_area`].
+    pub fn memory_rows(&self) -> u16 {
+        if self.orientation.swaps_axes() {
+            self.memory_size.width as u16
+        } else {
+            self.memory_size.height as u16
         }
     }
 
+    /// Row of the display memory shown in the top row of the display, in the current orientation
+    pub fn top_memory_row(&self) -> u16 {
+        self.y_offset
+    }
+
+    /// SPI device of the display, e.g. to lower the SPI clock while reading from the display
+    pub fn spi_device_mut(&mut self) -> &mut SPI {
+        &mut self.spi
+    }
+
     /// Runs commands to initialize the display in the default configuration for this library. In most use cases, this should
     /// be all that is needed to start and set-up the device.
     ///
@@ -143,9 +583,22 @@ where
     where
         D: DelayNs,
     {
-        self.hardware_reset(delay).await?;
-        self.software_reset(delay).await?;
-        self.set_orientation().await?;
+        if self.init_sequence != InitSequence::Reconfigure {
+            self.hardware_reset(delay).await?;
+            self.software_reset(delay).await?;
+        }
+
+        let (table, gamma) = match self.init_sequence {
+            InitSequence::Minimal | InitSequence::Reconfigure => (&[][..], self.gamma),
+            InitSequence::Vendor => (VENDOR_INIT, Some(self.gamma.unwrap_or_default())),
+            InitSequence::Custom(table) => (table, self.gamma),
+        };
+        self.run_init_table(table, delay).await?;
+        if let Some(gamma) = gamma {
+            self.set_gamma(&gamma).await?;
+        }
+
+        self.set_orientation(self.orientation).await?;
         self.set_pixel_format().await?;
         self.set_invert_mode().await?;
         self.set_sleep_mode(ModeState::Off, delay).await?;
@@ -195,36 +648,108 @@ where
         debug!("Software reset");
         self.send_command(command::SOFTWARE_RESET, &[]).await?;
         delay.delay_ms(120).await;
+
+        // The reset leaves the display sleeping, off and out of idle mode
+        self.sleep_mode = ModeState::On;
+        self.display_mode = ModeState::Off;
+        self.idle_mode = ModeState::Off;
         debug!("Software reset / done");
 
         Ok(())
     }
 
-    /// Set display orientation
+    /// Send the commands of an init table, waiting after each command as long as the table says
     ///
     /// # Errors
     ///
     /// Returns an error if any commands to the display fails
-    async fn set_orientation(&mut self) -> Result<(), Error> {
-        debug!("Set Orientation");
+    async fn run_init_table<D>(&mut self, table: &[InitCommand], delay: &mut D) -> Result<(), Error>
+    where
+        D: DelayNs,
+    {
+        debug!("Init table");
 
-        match self.orientation {
-            Orientation::Potrait => {
-                self.send_command(command::MEMORY_ACCESS_CONTROL, &[0x68])
-                    .await?
-            } //0x86
-            Orientation::Landscape => {
-                self.send_command(command::MEMORY_ACCESS_CONTROL, &[0x08])
-                    .await?
-            } //0x08
+        for step in table {
+            self.send_command(step.command, step.params).await?;
+            if step.delay_ms > 0 {
+                delay.delay_ms(step.delay_ms).await;
+            }
         }
 
+        debug!("Init table / done");
+
+        Ok(())
+    }
+
+    /// Set the positive and negative gamma correction curves
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any commands to the display fails
+    pub async fn set_gamma(&mut self, gamma: &GammaCurve) -> Result<(), Error> {
+        debug!("Set Gamma");
+        self.send_command(command::POSITIVE_GAMMA_CORRECTION, &gamma.positive)
+            .await?;
+        self.send_command(command::NEGATIVE_GAMMA_CORRECTION, &gamma.negative)
+            .await?;
+
+        debug!("Set Gamma / done");
+
+        Ok(())
+    }
+
+    /// Set display orientation, the size of the display is swapped when rotating by 90 or 270 degrees.
+    ///
+    /// The display memory is not redrawn, redraw the screen after changing the orientation.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any commands to the display fails
+    pub async fn set_orientation(&mut self, orientation: Orientation) -> Result<(), Error> {
+        debug!("Set Orientation");
+
+        self.send_command(command::MEMORY_ACCESS_CONTROL, &[orientation.madctl()])
+            .await?;
+
+        let size = orientation.size(self.panel_size);
+        self.orientation = orientation;
+        self.width = size.width as usize;
+        self.height = size.height as usize;
+        (self.x_offset, self.y_offset) = self.window_offset(orientation);
+
         debug!("Display orientation / done");
 
         Ok(())
     }
 
-    /// Set pixel format
+    /// Offsets of the column and row addresses of the window in the orientation.
+    ///
+    /// A mirrored axis counts from the other end of the display memory, so the offset
+    /// is the part of the memory after the panel, and the offsets are swapped with
+    /// the axes.
+    fn window_offset(&self, orientation: Orientation) -> (u16, u16) {
+        let madctl = orientation.madctl();
+        let (panel, memory) = (self.panel_size, self.memory_size);
+
+        let column_offset = if madctl & MADCTL_MX != 0 {
+            (memory.width - panel.width) as u16 - self.column_offset
+        } else {
+            self.column_offset
+        };
+        let row_offset = if madctl & MADCTL_MY != 0 {
+            (memory.height - panel.height) as u16 - self.row_offset
+        } else {
+            self.row_offset
+        };
+
+        if madctl & MADCTL_MV != 0 {
+            (row_offset, column_offset)
+        } else {
+            (column_offset, row_offset)
+        }
+    }
+
+    /// Set pixel format of the color format `C`
     /// 0x55 = 16 bits per pixels, 0x66 = 18 bits per pixel
     ///
     /// # Errors
@@ -232,7 +757,7 @@ where
     /// Returns an error if any commands to the display fails
     async fn set_pixel_format(&mut self) -> Result<(), Error> {
         debug!("Set Pixel Format");
-        self.send_command(command::PIXEL_FORMAT_SET, &[0x55])
+        self.send_command(command::PIXEL_FORMAT_SET, &[C::PIXEL_FORMAT])
             .await?;
 
         debug!("Display pixel format / done");
@@ -249,6 +774,8 @@ where
     where
         D: DelayNs,
     {
+        // The display needs 120ms after sleep out before it can sleep again, and
+        // 5ms after sleep in before it can wake up again
         match mode {
             ModeState::Off => {
                 debug!("Set Sleep Off");
@@ -262,6 +789,7 @@ where
                 delay.delay_ms(50).await;
             }
         }
+        self.sleep_mode = mode;
 
         debug!("Set Sleep Mode / done");
 
@@ -290,136 +818,1161 @@ where
                 delay.delay_ms(100).await;
             }
         }
+        self.display_mode = mode;
 
         debug!("Set Display Mode / done");
 
         Ok(())
     }
 
-    /// Invert pixel color on screen
+    /// Set idle mode, only 8 colors are shown to save power
     ///
     /// # Errors
     ///
     /// Returns an error if any commands to the display fails
-    async fn set_invert_mode(&mut self) -> Result<(), Error> {
-        match self.inverted {
+    async fn set_idle_mode(&mut self, mode: ModeState) -> Result<(), Error> {
+        match mode {
             ModeState::Off => {
-                debug!("Invert Off");
-                self.send_command(command::INVERT_OFF, &[]).await?;
+                debug!("Set Idle Off");
+                self.send_command(command::IDLE_MODE_OFF, &[]).await?;
             }
 
             ModeState::On => {
-                debug!("Invert On");
-                self.send_command(command::INVERT_ON, &[]).await?;
+                debug!("Set Idle On");
+                self.send_command(command::IDLE_MODE_ON, &[]).await?;
             }
         }
+        self.idle_mode = mode;
 
-        debug!("Set Display Mode / done");
+        debug!("Set Idle Mode / done");
 
         Ok(())
     }
 
-    /// Turn on backlght
+    /// Put the display to sleep, the display is blank and draws the least power.
+    ///
+    /// The display memory keeps its contents, writing pixels returns `Error::Asleep`
+    /// until the display wakes up.
     ///
     /// # Errors
     ///
-    /// Returns an error if setting any pin fails.
-    pub fn turn_on_backlight(&mut self) -> Result<(), Error> {
-        //async fn hardware_reset(&mut self) -> Result {
-        debug!("Turn on backlight");
-        self.power.set_high().map_err(Error::from_digital)?;
-        debug!("Turn on backlight / done");
-
-        Ok(())
+    /// Returns an error if any commands to the display fails
+    pub async fn sleep<D>(&mut self, delay: &mut D) -> Result<(), Error>
+    where
+        D: DelayNs,
+    {
+        if self.sleep_mode == ModeState::On {
+            return Ok(());
+        }
+        self.set_sleep_mode(ModeState::On, delay).await
     }
 
-    /// Send command over SPI bus
+    /// Wake the display up from sleep, it shows the display memory again.
+    ///
+    /// Returns after the 120ms the display needs before it can sleep again.
     ///
     /// # Errors
     ///
-    /// Returns an error if writing to SPI bus fails.
-    async fn send_command(&mut self, command: u8, data: &[u8]) -> Result<(), Error> {
-        //trace!("Set DC to low for transferring commands");
-        self.dc.set_low().map_err(Error::from_digital)?;
-        self.spi.write(&[command]).await?;
+    /// Returns an error if any commands to the display fails
+    pub async fn wake<D>(&mut self, delay: &mut D) -> Result<(), Error>
+    where
+        D: DelayNs,
+    {
+        if self.sleep_mode == ModeState::Off {
+            return Ok(());
+        }
+        self.set_sleep_mode(ModeState::Off, delay).await
+    }
 
-        if !data.is_empty() {
-            self.dc.set_high().map_err(Error::from_digital)?;
-            self.spi.write(data).await?;
+    /// Show only 8 colors, the most significant bit of each color, to save power
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any commands to the display fails
+    pub async fn enter_idle_mode(&mut self) -> Result<(), Error> {
+        if self.idle_mode == ModeState::On {
+            return Ok(());
         }
+        self.set_idle_mode(ModeState::On).await
+    }
 
-        Ok(())
+    /// Show all colors again
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any commands to the display fails
+    pub async fn exit_idle_mode(&mut self) -> Result<(), Error> {
+        if self.idle_mode == ModeState::Off {
+            return Ok(());
+        }
+        self.set_idle_mode(ModeState::Off).await
     }
 
-    /// Sends the data to the given area of the display's frame buffer.
+    /// Set the frame rate in idle mode, see the Frame Rate Control (In Idle Mode) command
+    /// in the datasheet.
     ///
-    /// The `area` is in your logical display coordinates; e.g if you use
-    /// if landscape the logical size is (320, 240) and the (0, 0) is the
-    /// top-right corner of the un-rotated physical screen.
+    /// # Parameters
     ///
-    /// You can fill the area using a smaller buffer by repeatedly calling
-    /// this method and passing the same `area`. Sending more data than fits
-    /// in the area will wrap around and overwrite the beginning of the area.
+    /// - `division_ratio`: Division of the internal clock, 0 to 3 for 1, 2, 4 or 8.
+    /// - `clocks_per_line`: Clocks per line, 16 to 31, more clocks lower the frame rate.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// If the area is empty or not completely contained within the display
-    /// bounds.
-    pub async fn write_pixels(&mut self, data: &[u8], area: Rectangle) -> Result<(), Error> {
-        //info!("area bottom right = {:?}", area.bottom_right().unwrap());
-        assert!(self.bounding_box().contains(area.top_left));
-        assert!(self.bounding_box().contains(area.bottom_right().unwrap()));
-
-        let area_x0: u16 = area.top_left.x.try_into().unwrap();
-        let area_y0: u16 = area.top_left.y.try_into().unwrap();
-        let area_x1: u16 = area.bottom_right().unwrap().x.try_into().unwrap();
-        let area_y1: u16 = area.bottom_right().unwrap().y.try_into().unwrap();
-
-        self.set_window(area_x0, area_y0, area_x1, area_y1).await?;
-        self.send_command(command::MEMORY_WRITE, &[]).await?;
-        self.dc.set_high().map_err(Error::from_digital)?;
-
-        self.spi.write(data).await?;
+    /// Returns an error if any commands to the display fails
+    pub async fn set_idle_frame_rate(
+        &mut self,
+        division_ratio: u8,
+        clocks_per_line: u8,
+    ) -> Result<(), Error> {
+        let params = [division_ratio & 0x03, clocks_per_line.clamp(16, 31)];
+        self.send_command(command::IDLE_MODE_FRAME_RATE, &params)
+            .await
+    }
 
-        Ok(())
+    /// Turn the display off, it shows a blank screen but keeps running
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any commands to the display fails
+    pub async fn display_off<D>(&mut self, delay: &mut D) -> Result<(), Error>
+    where
+        D: DelayNs,
+    {
+        if self.display_mode == ModeState::Off {
+            return Ok(());
+        }
+        self.set_display_mode(ModeState::Off, delay).await
     }
 
-    /// Set the window area where pixel data will be drawn on screen, represented by top-left corner (x0, y0)
-    /// and bottom-right corner (x1, y1).
-    async fn set_window(&mut self, x0: u16, y0: u16, x1: u16, y1: u16) -> Result<(), Error> {
-        //info!("x0 = {:?}  y0 = {:?}  x1 = {:?}  y1 = {:?}", x0, y0, x1, y1);
+    /// Turn the display on, it shows the display memory
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any commands to the display fails
+    pub async fn display_on<D>(&mut self, delay: &mut D) -> Result<(), Error>
+    where
+        D: DelayNs,
+    {
+        if self.display_mode == ModeState::On {
+            return Ok(());
+        }
+        self.set_display_mode(ModeState::On, delay).await
+    }
 
-        self.send_command(
-            command::COLUMN_ADDRESS_SET,
-            &[
-                (x0 >> 8) as u8,
-                (x0 & 0xff) as u8,
-                (x1 >> 8) as u8,
-                (x1 & 0xff) as u8,
-            ],
-        )
-        .await?;
-        self.send_command(
-            command::PAGE_ADDRESS_SET,
-            &[
-                (y0 >> 8) as u8,
-                (y0 & 0xff) as u8,
-                (y1 >> 8) as u8,
-                (y1 & 0xff) as u8,
-            ],
-        )
-        .await?;
+    /// Whether the display is sleeping
+    pub fn sleep_mode(&self) -> ModeState {
+        self.sleep_mode
+    }
 
-        Ok(())
+    /// Whether the display shows only 8 colors
+    pub fn idle_mode(&self) -> ModeState {
+        self.idle_mode
     }
-}
 
-/******************************************************************************************************
-*                                  IMPLEMENT CUSTOM ERRORS
-*****************************************************************************************************/
+    /// Whether the display is on
+    pub fn display_mode(&self) -> ModeState {
+        self.display_mode
+    }
 
-use embedded_hal::digital::Error as DigitalError;
+    /// Invert pixel color on screen
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any commands to the display fails
+    async fn set_invert_mode(&mut self) -> Result<(), Error> {
+        match self.inverted {
+            ModeState::Off => {
+                debug!("Invert Off");
+                self.send_command(command::INVERT_OFF, &[]).await?;
+            }
+
+            ModeState::On => {
+                debug!("Invert On");
+                self.send_command(command::INVERT_ON, &[]).await?;
+            }
+        }
+
+        debug!("Set Display Mode / done");
+
+        Ok(())
+    }
+
+    /// Turn on backlght
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if setting any pin fails.
+    pub fn turn_on_backlight(&mut self) -> Result<(), Error> {
+        //async fn hardware_reset(&mut self) -> Result {
+        debug!("Turn on backlight");
+        self.power.set_brightness(MAX_BRIGHTNESS)?;
+        debug!("Turn on backlight / done");
+
+        Ok(())
+    }
+
+    /// Turn off backlight
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if setting any pin fails.
+    pub fn turn_off_backlight(&mut self) -> Result<(), Error> {
+        debug!("Turn off backlight");
+        self.power.set_brightness(0)?;
+        debug!("Turn off backlight / done");
+
+        Ok(())
+    }
+
+    /// Set the brightness of the backlight in percent, an on/off backlight is on for any value above 0
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if setting the pin or PWM channel fails.
+    pub fn set_brightness(&mut self, percent: u8) -> Result<(), Error> {
+        self.power.set_brightness(percent)
+    }
+
+    /// Current brightness of the backlight in percent
+    pub fn brightness(&self) -> u8 {
+        self.power.brightness()
+    }
+
+    /// Change the brightness of the backlight gradually to `percent` over `duration_ms` milliseconds
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if setting the pin or PWM channel fails.
+    pub async fn fade_brightness<D>(
+        &mut self,
+        percent: u8,
+        duration_ms: u32,
+        delay: &mut D,
+    ) -> Result<(), Error>
+    where
+        D: DelayNs,
+    {
+        backlight::fade(&mut self.power, percent, duration_ms, delay).await
+    }
+
+    /// Set the brightness of the LEDPWM output of the display controller, 0 to 255.
+    ///
+    /// Only panels with their backlight driver connected to the LEDPWM pin of the
+    /// controller are dimmed by this, otherwise use the [`Backlight`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any commands to the display fails
+    pub async fn set_display_brightness(&mut self, brightness: u8) -> Result<(), Error> {
+        debug!("Set Display Brightness");
+        // Enable the brightness control block and the backlight control
+        self.send_command(command::WRITE_CTRL_DISPLAY, &[0x24])
+            .await?;
+        self.send_command(command::SET_BRIGHTNESS, &[brightness])
+            .await?;
+
+        debug!("Set Display Brightness / done");
+
+        Ok(())
+    }
+
+    /// Let the display controller lower the LEDPWM brightness depending on the image shown
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any commands to the display fails
+    pub async fn set_content_adaptive_brightness(
+        &mut self,
+        mode: AdaptiveBrightness,
+    ) -> Result<(), Error> {
+        debug!("Set Content Adaptive Brightness");
+        self.send_command(command::CONTENT_ADAPTIVE_BRIGHTNESS, &[mode as u8])
+            .await?;
+
+        debug!("Set Content Adaptive Brightness / done");
+
+        Ok(())
+    }
+
+    /// Split the display rows in a fixed area at the top, a vertical scrolling area and
+    /// a fixed area at the bottom.
+    ///
+    /// Only the rows of the scrolling area move when scrolling, the scrolled out rows
+    /// wrap around to the other side of the scrolling area. The rows are the rows of
+    /// the display memory in the current orientation, see [`Self::memory_rows`], so
+    /// rows of the memory outside of a smaller panel have to be in the fixed areas.
+    ///
+    /// # Parameters
+    ///
+    /// - `top_fixed`: Number of rows at the top that do not scroll.
+    /// - `scroll_height`: Number of rows that scroll.
+    /// - `bottom_fixed`: Number of rows at the bottom that do not scroll.
+    ///
+    /// # Errors
+    ///
+    /// - `Error::InvalidScrollArea` if the three areas do not add up to the rows of the
+    ///   display memory in the current orientation, nothing is sent.
+    /// - Any error of the commands to the display.
+    pub async fn define_scroll_area(
+        &mut self,
+        top_fixed: u16,
+        scroll_height: u16,
+        bottom_fixed: u16,
+    ) -> Result<(), Error> {
+        let rows = top_fixed as u32 + scroll_height as u32 + bottom_fixed as u32;
+        let height = self.memory_rows() as u32;
+        if rows != height {
+            return Err(Error::InvalidScrollArea { rows, height });
+        }
+
+        debug!("Define Scroll Area");
+        let mut data = [0; 6];
+        data[..2].copy_from_slice(&top_fixed.to_be_bytes());
+        data[2..4].copy_from_slice(&scroll_height.to_be_bytes());
+        data[4..].copy_from_slice(&bottom_fixed.to_be_bytes());
+        self.send_command(command::VERTICAL_SCROLL_DEFINE, &data)
+            .await?;
+
+        self.scroll_top = top_fixed;
+        self.scroll_height = scroll_height;
+
+        debug!("Define Scroll Area / done");
+
+        Ok(())
+    }
+
+    /// Scroll the scrolling area so `line` is shown at its top.
+    ///
+    /// The `line` is relative to the top of the scrolling area and wraps around at
+    /// its height, `0` shows the display memory unscrolled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any commands to the display fails
+    pub async fn scroll_to(&mut self, line: u16) -> Result<(), Error> {
+        let line = match self.scroll_height {
+            0 => 0,
+            height => line % height,
+        };
+        let start = self.scroll_top + line;
+        self.send_command(command::VERTICAL_SCROLL_ADDR, &start.to_be_bytes())
+            .await
+    }
+
+    /// Send command over SPI bus
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to SPI bus fails.
+    async fn send_command(&mut self, command: u8, data: &[u8]) -> Result<(), Error> {
+        //trace!("Set DC to low for transferring commands");
+        self.dc.set_low().map_err(Error::from_digital)?;
+        self.spi.write(&[command]).await?;
+
+        if !data.is_empty() {
+            self.dc.set_high().map_err(Error::from_digital)?;
+            self.spi.write(data).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends the data to the given area of the display's frame buffer.
+    ///
+    /// The `area` is in your logical display coordinates; e.g if you use
+    /// if landscape the logical size is (320, 240) and the (0, 0) is the
+    /// top-right corner of the un-rotated physical screen.
+    ///
+    /// The data holds exactly the pixels of the area in the color format `C`, see
+    /// [`InterfaceColor`]. Use [`Ili9341::write_pixels_repeating`] to fill the area
+    /// with a smaller buffer.
+    ///
+    /// An area that is not completely inside the display is handled as set by
+    /// [`ConfigBuilder::clip_mode`], an empty area sends nothing.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Asleep` if the display is sleeping, `Error::BufferSizeMismatch`
+    /// if the data does not have the size of the area, `Error::OutOfBounds` if the
+    /// area is not inside the display and clipping is off, or an error if writing
+    /// to SPI bus fails.
+    pub async fn write_pixels(&mut self, data: &[u8], area: Rectangle) -> Result<(), Error> {
+        self.write_area(data, area, area, self.clip_mode).await
+    }
+
+    /// Sends the `region` part of the data for the given area of the display, a
+    /// region that is not completely inside the display is handled as set by `clip_mode`.
+    ///
+    /// # Parameters
+    ///
+    /// - `data`: The pixels of the whole area.
+    /// - `area`: Area of the display the data covers.
+    /// - `region`: Part of the area that is sent.
+    /// - `clip_mode`: What to do with the pixels of the region outside of the display.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`Ili9341::write_pixels`].
+    async fn write_area(
+        &mut self,
+        data: &[u8],
+        area: Rectangle,
+        region: Rectangle,
+        clip_mode: ClipMode,
+    ) -> Result<(), Error> {
+        if self.sleep_mode == ModeState::On {
+            return Err(Error::Asleep);
+        }
+
+        let expected = area.size.width as usize * area.size.height as usize * C::BYTES_PER_PIXEL;
+        if data.len() != expected {
+            return Err(Error::BufferSizeMismatch {
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        if region.is_zero_sized() {
+            return Ok(());
+        }
+
+        let visible = region.intersection(&self.bounding_box());
+        if visible != region && clip_mode == ClipMode::Error {
+            return Err(Error::OutOfBounds { area: region });
+        }
+
+        if visible != area {
+            return self.write_visible_rows(data, area, visible).await;
+        }
+
+        self.start_memory_write(&area).await?;
+        self.write_chunks(data).await
+    }
+
+    /// Fills the given area of the display by sending `pattern` again and again,
+    /// e.g. a single pixel to fill the area with one color.
+    ///
+    /// The pattern holds whole pixels in the color format `C`. It is repeated row by
+    /// row from the top left pixel of the area, the last repetition is cut off when
+    /// the area is full. An area that is not completely inside the display is
+    /// handled as by [`Ili9341::write_pixels`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Asleep` if the display is sleeping, `Error::BufferSizeMismatch`
+    /// if the pattern is empty or not a whole number of pixels, `Error::OutOfBounds`
+    /// if the area is not inside the display and clipping is off, or an error if
+    /// writing to SPI bus fails.
+    pub async fn write_pixels_repeating(
+        &mut self,
+        pattern: &[u8],
+        area: Rectangle,
+    ) -> Result<(), Error> {
+        if self.sleep_mode == ModeState::On {
+            return Err(Error::Asleep);
+        }
+
+        let bpp = C::BYTES_PER_PIXEL;
+        let pattern_pixels = pattern.len() / bpp;
+        if pattern_pixels == 0 || pattern_pixels * bpp != pattern.len() {
+            return Err(Error::BufferSizeMismatch {
+                expected: pattern.len().div_ceil(bpp).max(1) * bpp,
+                actual: pattern.len(),
+            });
+        }
+
+        if area.is_zero_sized() {
+            return Ok(());
+        }
+
+        let visible = area.intersection(&self.bounding_box());
+        if visible != area && self.clip_mode == ClipMode::Error {
+            return Err(Error::OutOfBounds { area });
+        }
+
+        if visible.is_zero_sized() {
+            return Ok(());
+        }
+
+        // Repeat a short pattern in a buffer on the stack, so it is not sent a few bytes at a time
+        let mut buffer = [0_u8; PIXEL_CHUNK_SIZE * MAX_BYTES_PER_PIXEL];
+        let source = if pattern.len() <= buffer.len() {
+            let len = buffer.len() / pattern.len() * pattern.len();
+            for copy in buffer[..len].chunks_exact_mut(pattern.len()) {
+                copy.copy_from_slice(pattern);
+            }
+            &buffer[..len]
+        } else {
+            pattern
+        };
+
+        self.start_memory_write(&visible).await?;
+
+        // Full width rows follow each other in the area, they are sent at once
+        let offset = visible.top_left - area.top_left;
+        let (rows, row_pixels) = if visible.size.width == area.size.width {
+            (
+                1,
+                visible.size.width as usize * visible.size.height as usize,
+            )
+        } else {
+            (visible.size.height as usize, visible.size.width as usize)
+        };
+
+        for row in 0..rows {
+            let first = (offset.y as usize + row) * area.size.width as usize + offset.x as usize;
+            self.write_repeated(source, first % pattern_pixels * bpp, row_pixels * bpp)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Send `len` bytes of the endlessly repeated `source`, starting at byte `start` of it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to SPI bus fails.
+    async fn write_repeated(
+        &mut self,
+        source: &[u8],
+        mut start: usize,
+        mut len: usize,
+    ) -> Result<(), Error> {
+        while len > 0 {
+            let end = source.len().min(start + len).min(start + self.chunk_size);
+            self.spi.write(&source[start..end]).await?;
+
+            len -= end - start;
+            start = if end == source.len() { 0 } else { end };
+        }
+
+        Ok(())
+    }
+
+    /// Send the rows of the `visible` part of `area`, `data` holds the pixels of the whole area.
+    ///
+    /// The visible parts of the rows are copied next to each other in a buffer on
+    /// the stack, so narrow rows are not sent one at a time. Rows that do not fit
+    /// in the buffer are sent directly from the data.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to SPI bus fails.
+    async fn write_visible_rows(
+        &mut self,
+        data: &[u8],
+        area: Rectangle,
+        visible: Rectangle,
+    ) -> Result<(), Error> {
+        let stride = area.size.width as usize * C::BYTES_PER_PIXEL;
+
+        if visible.is_zero_sized() {
+            return Ok(());
+        }
+
+        let offset = visible.top_left - area.top_left;
+        let start = offset.x as usize * C::BYTES_PER_PIXEL;
+        let end = start + visible.size.width as usize * C::BYTES_PER_PIXEL;
+
+        self.start_memory_write(&visible).await?;
+
+        let mut buffer = [0_u8; PIXEL_CHUNK_SIZE * MAX_BYTES_PER_PIXEL];
+        let mut len = 0;
+
+        for row in data
+            .chunks_exact(stride)
+            .skip(offset.y as usize)
+            .take(visible.size.height as usize)
+        {
+            let row = &row[start..end];
+
+            if len + row.len() > buffer.len() {
+                self.write_chunks(&buffer[..len]).await?;
+                len = 0;
+            }
+
+            if row.len() > buffer.len() {
+                self.write_chunks(row).await?;
+            } else {
+                buffer[len..len + row.len()].copy_from_slice(row);
+                len += row.len();
+            }
+        }
+
+        self.write_chunks(&buffer[..len]).await
+    }
+
+    /// Send pixel data of a memory write in chunks of at most the chunk size
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to SPI bus fails.
+    async fn write_chunks(&mut self, data: &[u8]) -> Result<(), Error> {
+        for chunk in data.chunks(self.chunk_size) {
+            self.spi.write(chunk).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the window to `area` and start a memory write, the pixel data is sent next.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::OutOfBounds` if the area is empty or outside of the display
+    /// memory, or an error if writing to SPI bus fails.
+    async fn start_memory_write(&mut self, area: &Rectangle) -> Result<(), Error> {
+        self.set_window_area(area).await?;
+        self.send_command(command::MEMORY_WRITE, &[]).await?;
+        self.dc.set_high().map_err(Error::from_digital)?;
+
+        Ok(())
+    }
+
+    /// Set the window to the non-empty `area`
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::OutOfBounds` if the area is empty or outside of the display
+    /// memory, or an error if writing to SPI bus fails.
+    async fn set_window_area(&mut self, area: &Rectangle) -> Result<(), Error> {
+        let out_of_bounds = || Error::OutOfBounds { area: *area };
+        let bottom_right = area.bottom_right().ok_or_else(out_of_bounds)?;
+
+        let area_x0 = u16::try_from(area.top_left.x).map_err(|_| out_of_bounds())?;
+        let area_y0 = u16::try_from(area.top_left.y).map_err(|_| out_of_bounds())?;
+        let area_x1 = u16::try_from(bottom_right.x).map_err(|_| out_of_bounds())?;
+        let area_y1 = u16::try_from(bottom_right.y).map_err(|_| out_of_bounds())?;
+
+        self.set_window(area_x0, area_y0, area_x1, area_y1).await
+    }
+
+    /// Set the window area where pixel data will be drawn on screen, represented by top-left corner (x0, y0)
+    /// and bottom-right corner (x1, y1).
+    async fn set_window(&mut self, x0: u16, y0: u16, x1: u16, y1: u16) -> Result<(), Error> {
+        //info!("x0 = {:?}  y0 = {:?}  x1 = {:?}  y1 = {:?}", x0, y0, x1, y1);
+
+        let (x0, x1) = (x0 + self.x_offset, x1 + self.x_offset);
+        let (y0, y1) = (y0 + self.y_offset, y1 + self.y_offset);
+
+        self.send_command(command::COLUMN_ADDRESS_SET, &address_range(x0, x1))
+            .await?;
+        self.send_command(command::PAGE_ADDRESS_SET, &address_range(y0, y1))
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Parameters of the column and page address set commands, the start and end address
+fn address_range(start: u16, end: u16) -> [u8; 4] {
+    [
+        (start >> 8) as u8,
+        (start & 0xff) as u8,
+        (end >> 8) as u8,
+        (end & 0xff) as u8,
+    ]
+}
+
+/******************************************************************************************************
+*                                  IMPLEMENT PANEL READBACK
+*****************************************************************************************************/
+
+/// Identification of the display returned by the Read Display Identification Information command.
+///
+/// The values are programmed by the module vendor, many ILI9341 modules return zeros.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DisplayId {
+    /// LCD module manufacturer ID.
+    pub manufacturer: u8,
+    /// LCD module / driver version ID.
+    pub version: u8,
+    /// LCD module / driver ID.
+    pub driver: u8,
+}
+
+/// Status of the display returned by the Read Display Status command.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DisplayStatus(pub u32);
+
+impl DisplayStatus {
+    /// Memory Access Control the display uses, the MY, MX, MV, ML, BGR and MH bits
+    pub const fn madctl(&self) -> u8 {
+        (self.0 >> 23) as u8 & 0xfc
+    }
+
+    /// Interface pixel format, 0b101 = 16 bits per pixel, 0b110 = 18 bits per pixel
+    pub const fn pixel_format(&self) -> u8 {
+        (self.0 >> 20) as u8 & 0x07
+    }
+
+    /// Whether the booster voltage is on
+    pub const fn booster_on(&self) -> bool {
+        self.0 & (1 << 31) != 0
+    }
+
+    /// Whether the display shows 8 colors to save power
+    pub const fn idle_mode(&self) -> ModeState {
+        mode_state(self.0 & (1 << 19) != 0)
+    }
+
+    /// Whether the display is sleeping
+    pub const fn sleep_mode(&self) -> ModeState {
+        // The status has the sleep out bit
+        mode_state(self.0 & (1 << 17) == 0)
+    }
+
+    /// Whether the colors are inverted
+    pub const fn inversion_mode(&self) -> ModeState {
+        mode_state(self.0 & (1 << 13) != 0)
+    }
+
+    /// Whether the display shows the display memory
+    pub const fn display_mode(&self) -> ModeState {
+        mode_state(self.0 & (1 << 10) != 0)
+    }
+}
+
+/// Mode state of a status bit
+const fn mode_state(on: bool) -> ModeState {
+    if on {
+        ModeState::On
+    } else {
+        ModeState::Off
+    }
+}
+
+/// Reading from the display needs the MISO line of the SPI bus connected to the
+/// SDO pin of the display. The display reads at most at about 6.6MHz, a faster
+/// SPI clock may return corrupted values.
+impl<SPI, DC, RST, PO, C> Ili9341<SPI, DC, RST, PO, C>
+where
+    SPI: SpiDevice,
+    DC: OutputPin,
+    RST: OutputPin,
+    PO: Backlight,
+    C: InterfaceColor,
+{
+    /// Read the identification of the display
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any commands to the display fails
+    pub async fn read_id(&mut self) -> Result<DisplayId, Error> {
+        let mut id = [0; 3];
+        self.read_register(command::READ_DISPLAY_ID, &mut id)
+            .await?;
+
+        Ok(DisplayId {
+            manufacturer: id[0],
+            version: id[1],
+            driver: id[2],
+        })
+    }
+
+    /// Read the status of the display
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any commands to the display fails
+    pub async fn read_status(&mut self) -> Result<DisplayStatus, Error> {
+        let mut status = [0; 4];
+        self.read_register(command::READ_DISPLAY_STATUS, &mut status)
+            .await?;
+
+        Ok(DisplayStatus(u32::from_be_bytes(status)))
+    }
+
+    /// Read the Memory Access Control the display uses, see [`Orientation::madctl`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any commands to the display fails
+    pub async fn read_madctl(&mut self) -> Result<u8, Error> {
+        let mut madctl = [0; 1];
+        self.read_register(command::READ_DISPLAY_MADCTL, &mut madctl)
+            .await?;
+
+        Ok(madctl[0])
+    }
+
+    /// Read the pixel format of the display, 0x55 = 16 bits per pixels, 0x66 = 18 bits per pixel
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any commands to the display fails
+    pub async fn read_pixel_format(&mut self) -> Result<u8, Error> {
+        let mut format = [0; 1];
+        self.read_register(command::READ_DISPLAY_PIXEL_FORMAT, &mut format)
+            .await?;
+
+        Ok(format[0])
+    }
+
+    /// Whether a display answers on the SPI bus.
+    ///
+    /// Without a display the MISO line reads all zeros or all ones, the status of
+    /// an ILI9341 always has some bits set and some reserved bits cleared.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any commands to the display fails
+    pub async fn is_attached(&mut self) -> Result<bool, Error> {
+        let status = self.read_status().await?;
+
+        Ok(status.0 != 0 && status.0 != u32::MAX)
+    }
+
+    /// Check that the display uses the orientation, pixel format and modes set by the driver, e.g. after [`Ili9341::initialize`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any commands to the display fails
+    pub async fn verify_configuration(&mut self) -> Result<bool, Error> {
+        let madctl = self.read_madctl().await?;
+        let format = self.read_pixel_format().await?;
+        let status = self.read_status().await?;
+        debug!(
+            "Display MADCTL = {:#04x}, pixel format = {:#04x}, status = {:#010x}",
+            madctl, format, status.0
+        );
+
+        // Only the MCU interface format is used over SPI
+        Ok(madctl == self.orientation.madctl()
+            && format & 0x07 == C::PIXEL_FORMAT & 0x07
+            && status.inversion_mode() == self.inverted
+            && status.sleep_mode() == self.sleep_mode
+            && status.idle_mode() == self.idle_mode
+            && status.display_mode() == self.display_mode)
+    }
+
+    /// Reads the pixels of the given area of the display's frame buffer, e.g. to take a screenshot.
+    ///
+    /// The pixels are stored in the same format as they are sent by
+    /// [`Ili9341::write_pixels`], in the color format `C`, row by row.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::OutOfBounds` if the area is not inside the display,
+    /// `Error::BufferSizeMismatch` if `data` cannot hold the pixels of the area, or
+    /// an error if any commands to the display fails.
+    pub async fn read_pixels(&mut self, area: Rectangle, data: &mut [u8]) -> Result<(), Error> {
+        if area.is_zero_sized() {
+            return Ok(());
+        }
+
+        if area.intersection(&self.bounding_box()) != area {
+            return Err(Error::OutOfBounds { area });
+        }
+
+        let len = area.size.width as usize * area.size.height as usize * C::BYTES_PER_PIXEL;
+        if data.len() < len {
+            return Err(Error::BufferSizeMismatch {
+                expected: len,
+                actual: data.len(),
+            });
+        }
+
+        self.set_window_area(&area).await?;
+
+        // The first chunk starts at the beginning of the window, the next ones
+        // continue after the last pixel read
+        let mut read_command = command::MEMORY_READ;
+        let mut rgb = [0_u8; READ_PIXEL_CHUNK_SIZE * 3];
+
+        for chunk in data[..len].chunks_mut(READ_PIXEL_CHUNK_SIZE * C::BYTES_PER_PIXEL) {
+            let rgb = &mut rgb[..chunk.len() / C::BYTES_PER_PIXEL * 3];
+
+            // A memory read starts with a dummy byte, then the display sends 3 bytes per
+            // pixel with the 6 bits of every color in the upper bits, whatever the pixel format
+            let mut dummy = [0; 1];
+            self.dc.set_low().map_err(Error::from_digital)?;
+            self.spi
+                .transaction(&mut [
+                    Operation::Write(&[read_command]),
+                    Operation::Read(&mut dummy),
+                    Operation::Read(rgb),
+                ])
+                .await?;
+
+            for (pixel, rgb) in chunk
+                .chunks_exact_mut(C::BYTES_PER_PIXEL)
+                .zip(rgb.chunks_exact(3))
+            {
+                let color = C::from(Rgb666::new(rgb[0] >> 2, rgb[1] >> 2, rgb[2] >> 2));
+                pixel.copy_from_slice(color.to_interface_bytes().as_ref());
+            }
+
+            read_command = command::MEMORY_READ_CONTINUE;
+        }
+
+        Ok(())
+    }
+
+    /// Send a read command and read its parameters.
+    ///
+    /// The command and the read are a single SPI transaction, the chip select has to
+    /// stay low until the parameters are read. Over SPI the display inserts a single
+    /// dummy clock before the parameters of the 24 and 32 bit reads, so they are read
+    /// one bit shifted and realigned here. The 8 bit reads have no dummy clock.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any commands to the display fails
+    ///
+    /// # Panics
+    ///
+    /// If more than 4 bytes are read.
+    async fn read_register(&mut self, command: u8, data: &mut [u8]) -> Result<(), Error> {
+        assert!(data.len() <= 4);
+
+        // One more byte holds the last bit of the parameters after the dummy clock
+        let dummy_clock = data.len() > 1;
+        let mut raw = [0_u8; 5];
+        let raw = &mut raw[..data.len() + usize::from(dummy_clock)];
+
+        self.dc.set_low().map_err(Error::from_digital)?;
+        self.spi
+            .transaction(&mut [Operation::Write(&[command]), Operation::Read(raw)])
+            .await?;
+
+        if dummy_clock {
+            for (i, byte) in data.iter_mut().enumerate() {
+                *byte = (raw[i] << 1) | (raw[i + 1] >> 7);
+            }
+        } else {
+            data.copy_from_slice(raw);
+        }
+
+        Ok(())
+    }
+}
+
+/******************************************************************************************************
+*                                  IMPLEMENT DRAW TARGET
+*****************************************************************************************************/
+
+use embedded_hal::spi::SpiDevice as BlockingSpiDevice;
+
+/// Blocking helpers for the [`DrawTarget`] implementation, the embedded-graphics
+/// drawing methods are not async so the pixels are written with the blocking SPI
+/// traits of the same device.
+impl<SPI, DC, RST, PO, C> Ili9341<SPI, DC, RST, PO, C>
+where
+    SPI: SpiDevice + BlockingSpiDevice,
+    DC: OutputPin,
+    RST: OutputPin,
+    PO: Backlight,
+    C: InterfaceColor,
+{
+    /// Send command over SPI bus, blocking until it is sent
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to SPI bus fails.
+    fn send_command_blocking(&mut self, command: u8, data: &[u8]) -> Result<(), Error> {
+        self.dc.set_low().map_err(Error::from_digital)?;
+        BlockingSpiDevice::write(&mut self.spi, &[command])?;
+
+        if !data.is_empty() {
+            self.dc.set_high().map_err(Error::from_digital)?;
+            BlockingSpiDevice::write(&mut self.spi, data)?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the window to `area` and start a memory write, the pixel data is sent
+    /// next with [`Self::write_data_blocking`].
+    ///
+    /// The area must not be empty and be completely inside the display.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Asleep` if the display is sleeping, or an error if writing to SPI bus fails.
+    fn start_memory_write_blocking(&mut self, area: &Rectangle) -> Result<(), Error> {
+        if self.sleep_mode == ModeState::On {
+            return Err(Error::Asleep);
+        }
+
+        let bottom_right = area.bottom_right().unwrap_or(area.top_left);
+
+        self.send_command_blocking(
+            command::COLUMN_ADDRESS_SET,
+            &address_range(
+                area.top_left.x as u16 + self.x_offset,
+                bottom_right.x as u16 + self.x_offset,
+            ),
+        )?;
+        self.send_command_blocking(
+            command::PAGE_ADDRESS_SET,
+            &address_range(
+                area.top_left.y as u16 + self.y_offset,
+                bottom_right.y as u16 + self.y_offset,
+            ),
+        )?;
+        self.send_command_blocking(command::MEMORY_WRITE, &[])?;
+        self.dc.set_high().map_err(Error::from_digital)?;
+
+        Ok(())
+    }
+
+    /// Send pixel data of a memory write started by [`Self::start_memory_write_blocking`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to SPI bus fails.
+    fn write_data_blocking(&mut self, data: &[u8]) -> Result<(), Error> {
+        for chunk in data.chunks(self.chunk_size) {
+            BlockingSpiDevice::write(&mut self.spi, chunk)?;
+        }
+
+        Ok(())
+    }
+
+    /// Send a run of pixels on one row, starting at `start`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to SPI bus fails.
+    fn write_row_blocking(&mut self, start: Point, data: &[u8]) -> Result<(), Error> {
+        let width = (data.len() / C::BYTES_PER_PIXEL) as u32;
+        self.start_memory_write_blocking(&Rectangle::new(start, Size::new(width, 1)))?;
+        self.write_data_blocking(data)
+    }
+}
+
+/// Draw directly on the display without a [`Framebuffer`].
+///
+/// Every drawing call sets a window and streams the pixels into it, so large
+/// single color areas, like clearing the screen, do not need any pixel buffer.
+/// Pixels outside of the display are dropped.
+impl<SPI, DC, RST, PO, C> DrawTarget for Ili9341<SPI, DC, RST, PO, C>
+where
+    SPI: SpiDevice + BlockingSpiDevice,
+    DC: OutputPin,
+    RST: OutputPin,
+    PO: Backlight,
+    C: InterfaceColor,
+{
+    type Color = C;
+    type Error = Error;
+
+    /// Consecutive pixels on the same row are sent as one run with a single window.
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let bounds = self.bounding_box();
+        let bpp = C::BYTES_PER_PIXEL;
+        let mut run = [0u8; PIXEL_CHUNK_SIZE * MAX_BYTES_PER_PIXEL];
+        let mut run_start = Point::zero();
+        let mut run_len = 0;
+
+        for Pixel(point, color) in pixels {
+            if !bounds.contains(point) {
+                continue;
+            }
+
+            // Send the run when the pixel does not continue it
+            let next = run_start + Point::new(run_len as i32, 0);
+            if run_len > 0 && (point != next || run_len == PIXEL_CHUNK_SIZE) {
+                self.write_row_blocking(run_start, &run[..run_len * bpp])?;
+                run_len = 0;
+            }
+
+            if run_len == 0 {
+                run_start = point;
+            }
+            run[run_len * bpp..(run_len + 1) * bpp]
+                .copy_from_slice(color.to_interface_bytes().as_ref());
+            run_len += 1;
+        }
+
+        if run_len > 0 {
+            self.write_row_blocking(run_start, &run[..run_len * bpp])?;
+        }
+
+        Ok(())
+    }
+
+    /// Areas inside the display are streamed into a single window, areas crossing
+    /// the edge of the display are drawn as rows of the visible pixels.
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        if area.is_zero_sized() {
+            return Ok(());
+        }
+
+        if area.intersection(&self.bounding_box()) != *area {
+            return self.draw_iter(
+                area.points()
+                    .zip(colors)
+                    .map(|(point, color)| Pixel(point, color)),
+            );
+        }
+
+        self.start_memory_write_blocking(area)?;
+
+        let bpp = C::BYTES_PER_PIXEL;
+        let pixel_count = area.size.width as usize * area.size.height as usize;
+        let mut chunk = [0u8; PIXEL_CHUNK_SIZE * MAX_BYTES_PER_PIXEL];
+        let mut len = 0;
+
+        for color in colors.into_iter().take(pixel_count) {
+            chunk[len * bpp..(len + 1) * bpp].copy_from_slice(color.to_interface_bytes().as_ref());
+            len += 1;
+
+            if len == PIXEL_CHUNK_SIZE {
+                self.write_data_blocking(&chunk[..len * bpp])?;
+                len = 0;
+            }
+        }
+
+        if len > 0 {
+            self.write_data_blocking(&chunk[..len * bpp])?;
+        }
+
+        Ok(())
+    }
+
+    /// The window is set once and the same chunk of pixels is sent until it is full.
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        if area.is_zero_sized() {
+            return Ok(());
+        }
+
+        self.start_memory_write_blocking(&area)?;
+
+        let bpp = C::BYTES_PER_PIXEL;
+        let mut chunk = [0u8; PIXEL_CHUNK_SIZE * MAX_BYTES_PER_PIXEL];
+        for pixel in chunk[..PIXEL_CHUNK_SIZE * bpp].chunks_exact_mut(bpp) {
+            pixel.copy_from_slice(color.to_interface_bytes().as_ref());
+        }
+
+        let mut remaining = area.size.width as usize * area.size.height as usize;
+        while remaining > 0 {
+            let len = remaining.min(PIXEL_CHUNK_SIZE);
+            self.write_data_blocking(&chunk[..len * bpp])?;
+            remaining -= len;
+        }
+
+        Ok(())
+    }
+}
+
+/******************************************************************************************************
+*                                  IMPLEMENT CUSTOM ERRORS
+*****************************************************************************************************/
+
+use embedded_hal::digital::Error as DigitalError;
 use embedded_hal::digital::ErrorKind as DigitalErrorKind;
+use embedded_hal::pwm::Error as PwmError;
+use embedded_hal::pwm::ErrorKind as PwmErrorKind;
 use embedded_hal::spi::Error as SpiError;
 use embedded_hal::spi::ErrorKind as SpiErrorKind;
 
@@ -431,6 +1984,34 @@ pub enum Error {
 
     /// An error in the underlying digital system
     Digital(DigitalErrorKind),
+
+    /// An error in the PWM channel of the backlight
+    Pwm(PwmErrorKind),
+
+    /// Pixels were written while the display is sleeping
+    Asleep,
+
+    /// The area is not inside the display
+    OutOfBounds {
+        /// Area of the rejected write or read
+        area: Rectangle,
+    },
+
+    /// The pixel data does not have the size the area needs
+    BufferSizeMismatch {
+        /// Number of bytes the area needs
+        expected: usize,
+        /// Number of bytes of the pixel data
+        actual: usize,
+    },
+
+    /// The fixed and scrolling areas do not add up to the rows of the display memory
+    InvalidScrollArea {
+        /// Number of rows of the three areas together
+        rows: u32,
+        /// Rows of the display memory in the current orientation
+        height: u32,
+    },
 }
 
 impl<E> From<E> for Error
@@ -451,6 +2032,15 @@ impl Error {
     {
         Self::Digital(error.kind())
     }
+
+    /// Convert a PWM error to an error
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn from_pwm<E>(error: E) -> Self
+    where
+        E: PwmError,
+    {
+        Self::Pwm(error.kind())
+    }
 }
 
 /******************************************************************************************************
@@ -458,33 +2048,802 @@ impl Error {
 *****************************************************************************************************/
 /// Convenience trait to hide details of the driver type.
 ///
-/// Once the display driver is created, only the error type depends on the HAL
-/// types used for the implementation. For the use cases where panic on error
-/// is acceptable, we can ignore the type parameters.
+/// Once the display driver is created, only the error type and the color format
+/// depend on the types used for the implementation, so the code sending pixels
+/// does not need the type parameters of the driver.
 #[allow(async_fn_in_trait)]
 pub trait WritePixels {
-    async fn write_pixels(&mut self, data: &[u8], area: Rectangle);
+    /// Error returned when the pixels cannot be sent.
+    type Error;
+
+    /// Color format of the pixel data sent to the display.
+    type Color: InterfaceColor;
+
+    /// Sends the data to the given area of the display.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pixels cannot be sent.
+    async fn write_pixels(&mut self, data: &[u8], area: Rectangle) -> Result<(), Self::Error>;
 
     /// Transfers the contents of the framebuffer to the display.
-    async fn flush<C>(&mut self, fb: &Framebuffer<'_, C>, top_left: Point)
-    where
-        C: PixelColor + ToBytes,
-    {
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pixels cannot be sent.
+    async fn flush(
+        &mut self,
+        fb: &Framebuffer<'_, Self::Color>,
+        top_left: Point,
+    ) -> Result<(), Self::Error> {
         self.write_pixels(fb.data(), Rectangle::new(top_left, fb.size()))
             .await
     }
+
+    /// Transfers the `src` rectangle of the framebuffer to the display, with its
+    /// top left pixel at `dest`, e.g. only the digit that changed in a strip.
+    ///
+    /// Only the part of `src` inside the framebuffer is sent. The default
+    /// implementation sends every row of the rectangle with a separate write.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pixels cannot be sent.
+    async fn flush_region(
+        &mut self,
+        fb: &Framebuffer<'_, Self::Color>,
+        src: Rectangle,
+        dest: Point,
+    ) -> Result<(), Self::Error> {
+        let region = src.intersection(&fb.bounding_box());
+        if region.is_zero_sized() {
+            return Ok(());
+        }
+
+        let bpp = Self::Color::BYTES_PER_PIXEL;
+        let dest = dest + (region.top_left - src.top_left);
+        let stride = fb.size().width as usize * bpp;
+        let start = region.top_left.x as usize * bpp;
+        let end = start + region.size.width as usize * bpp;
+
+        for (y, row) in fb
+            .data()
+            .chunks_exact(stride)
+            .skip(region.top_left.y as usize)
+            .take(region.size.height as usize)
+            .enumerate()
+        {
+            let area = Rectangle::new(
+                dest + Point::new(0, y as i32),
+                Size::new(region.size.width, 1),
+            );
+            self.write_pixels(&row[start..end], area).await?;
+        }
+
+        Ok(())
+    }
 }
 
-impl<SPI, DC, RST, PO> WritePixels for Ili9341<SPI, DC, RST, PO>
+impl<SPI, DC, RST, PO, C> WritePixels for Ili9341<SPI, DC, RST, PO, C>
 where
     SPI: SpiDevice,
     DC: OutputPin,
     RST: OutputPin,
-    PO: OutputPin,
+    PO: Backlight,
+    C: InterfaceColor,
 {
-    async fn write_pixels(&mut self, data: &[u8], area: Rectangle) {
-        self.write_pixels(data, area)
+    type Error = Error;
+    type Color = C;
+
+    async fn write_pixels(&mut self, data: &[u8], area: Rectangle) -> Result<(), Self::Error> {
+        self.write_pixels(data, area).await
+    }
+
+    /// Only the part of the framebuffer inside the display is sent, whatever the
+    /// clip mode, so a framebuffer can move on and off the edge of the display,
+    /// e.g. a sprite or a slide-in animation.
+    async fn flush(&mut self, fb: &Framebuffer<'_, C>, top_left: Point) -> Result<(), Self::Error> {
+        let area = Rectangle::new(top_left, fb.size());
+        self.write_area(fb.data(), area, area, ClipMode::Clip).await
+    }
+
+    /// The rectangle is sent with a single window, its rows are copied next to each
+    /// other as for [`WritePixels::flush`], and only the part inside the display is sent.
+    async fn flush_region(
+        &mut self,
+        fb: &Framebuffer<'_, C>,
+        src: Rectangle,
+        dest: Point,
+    ) -> Result<(), Self::Error> {
+        // Place the whole framebuffer so the top left pixel of `src` is at `dest`
+        let area = Rectangle::new(dest - src.top_left, fb.size());
+        let region = Rectangle::new(dest, src.size).intersection(&area);
+        self.write_area(fb.data(), area, region, ClipMode::Clip)
             .await
-            .unwrap_or_else(|_| panic!("write_pixels failed"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+    use std::vec::Vec;
+
+    use embedded_graphics_core::pixelcolor::raw::RawU16;
+
+    use super::*;
+    use crate::init_table::VENDOR_GAMMA;
+    use crate::test_util::{block_on, mock_display, Event, MockDisplay, Recorder};
+
+    fn command(command: u8, params: &[u8]) -> Event {
+        Event::Command {
+            command,
+            params: params.to_vec(),
+        }
+    }
+
+    /// Events of `initialize` with the config
+    fn initialize(config: Config) -> Vec<Event> {
+        let (mut display, mut delay, recorder) = mock_display::<Rgb565>(config);
+        block_on(display.initialize(&mut delay)).unwrap();
+        recorder.take()
+    }
+
+    /// Events of the hardware and software reset
+    fn reset() -> Vec<Event> {
+        vec![
+            Event::Reset(false),
+            Event::Delay { ms: 1 },
+            Event::Reset(true),
+            Event::Delay { ms: 5 },
+            command(command::SOFTWARE_RESET, &[]),
+            Event::Delay { ms: 120 },
+        ]
+    }
+
+    /// Events after the init table and the gamma curves, for the default config
+    fn configure() -> Vec<Event> {
+        vec![
+            command(command::MEMORY_ACCESS_CONTROL, &[MADCTL_BGR]),
+            command(command::PIXEL_FORMAT_SET, &[0x55]),
+            command(command::INVERT_ON, &[]),
+            command(command::SLEEP_MODE_OFF, &[]),
+            Event::Delay { ms: 150 },
+            command(command::DISPLAY_ON, &[]),
+            Event::Delay { ms: 100 },
+        ]
+    }
+
+    #[test]
+    fn initialize_minimal() {
+        let expected = [reset(), configure()].concat();
+
+        assert_eq!(initialize(Config::default()), expected);
+    }
+
+    #[test]
+    fn initialize_reconfigure_does_not_reset() {
+        let config = Config::builder()
+            .init_sequence(InitSequence::Reconfigure)
+            .build()
+            .unwrap();
+
+        assert_eq!(initialize(config), configure());
+    }
+
+    #[test]
+    fn initialize_vendor_with_custom_gamma() {
+        let gamma = GammaCurve {
+            positive: [0x11; 15],
+            negative: [0x22; 15],
+        };
+        let config = Config::builder()
+            .init_sequence(InitSequence::Vendor)
+            .gamma(gamma)
+            .build()
+            .unwrap();
+
+        let table: Vec<Event> = VENDOR_INIT
+            .iter()
+            .map(|step| command(step.command, step.params))
+            .collect();
+        let gamma = vec![
+            command(command::POSITIVE_GAMMA_CORRECTION, &[0x11; 15]),
+            command(command::NEGATIVE_GAMMA_CORRECTION, &[0x22; 15]),
+        ];
+        let expected = [reset(), table, gamma, configure()].concat();
+
+        assert_eq!(initialize(config), expected);
+    }
+
+    #[test]
+    fn initialize_vendor_uses_vendor_gamma_by_default() {
+        let config = Config::builder()
+            .init_sequence(InitSequence::Vendor)
+            .build()
+            .unwrap();
+
+        let events = initialize(config);
+        let gamma = &events[reset().len() + VENDOR_INIT.len()..][..2];
+        assert_eq!(
+            gamma,
+            [
+                command(command::POSITIVE_GAMMA_CORRECTION, &VENDOR_GAMMA.positive),
+                command(command::NEGATIVE_GAMMA_CORRECTION, &VENDOR_GAMMA.negative),
+            ]
+        );
+    }
+
+    #[test]
+    fn initialize_custom_table_waits_after_a_step() {
+        static TABLE: &[InitCommand] = &[
+            InitCommand::new(command::FRAME_RATE_CONTROL_1, &[0x00, 0x1b]),
+            InitCommand::new(command::GAMMA_SET, &[0x01]).with_delay(10),
+        ];
+        let config = Config::builder()
+            .init_sequence(InitSequence::Custom(TABLE))
+            .build()
+            .unwrap();
+
+        let table = vec![
+            command(command::FRAME_RATE_CONTROL_1, &[0x00, 0x1b]),
+            command(command::GAMMA_SET, &[0x01]),
+            Event::Delay { ms: 10 },
+        ];
+        let expected = [reset(), table, configure()].concat();
+
+        assert_eq!(initialize(config), expected);
+    }
+
+    /// Initialized display, with the initialization removed from the recording
+    fn initialized(config: Config) -> (MockDisplay, Recorder) {
+        let (mut display, mut delay, recorder) = mock_display(config);
+        block_on(display.initialize(&mut delay)).unwrap();
+        recorder.take();
+
+        (display, recorder)
+    }
+
+    /// Events of a memory write of `data` to the window from `(x0, y0)` to `(x1, y1)`
+    fn memory_write(x0: u16, y0: u16, x1: u16, y1: u16, data: &[u8]) -> Vec<Event> {
+        vec![
+            command(command::COLUMN_ADDRESS_SET, &address_range(x0, x1)),
+            command(command::PAGE_ADDRESS_SET, &address_range(y0, y1)),
+            command(command::MEMORY_WRITE, data),
+        ]
+    }
+
+    #[test]
+    fn write_pixels_rejects_short_and_long_data() {
+        let (mut display, recorder) = initialized(Config::default());
+        let area = Rectangle::new(Point::new(10, 20), Size::new(2, 2));
+
+        assert_eq!(
+            block_on(display.write_pixels(&[0; 6], area)),
+            Err(Error::BufferSizeMismatch {
+                expected: 8,
+                actual: 6
+            })
+        );
+        assert_eq!(
+            block_on(display.write_pixels(&[0; 10], area)),
+            Err(Error::BufferSizeMismatch {
+                expected: 8,
+                actual: 10
+            })
+        );
+        assert_eq!(recorder.take(), []);
+    }
+
+    #[test]
+    fn write_pixels_sends_exact_size_data() {
+        let (mut display, recorder) = initialized(Config::default());
+        let data = [1, 2, 3, 4, 5, 6, 7, 8];
+        let area = Rectangle::new(Point::new(10, 20), Size::new(2, 2));
+
+        block_on(display.write_pixels(&data, area)).unwrap();
+
+        assert_eq!(recorder.take(), memory_write(10, 20, 11, 21, &data));
+    }
+
+    #[test]
+    fn write_pixels_repeating_continues_the_pattern_across_a_clipped_window() {
+        let config = Config::builder().clip_mode(ClipMode::Clip).build().unwrap();
+        let (mut display, recorder) = initialized(config);
+        let (a, b) = ([0xaa, 0xaa], [0xbb, 0xbb]);
+
+        // 3x2 pixels with the left column and the bottom row outside of the display:
+        // A B A
+        // B A B
+        let area = Rectangle::new(Point::new(-1, 239), Size::new(3, 2));
+        block_on(display.write_pixels_repeating(&[a, b].concat(), area)).unwrap();
+
+        assert_eq!(
+            recorder.take(),
+            memory_write(0, 239, 1, 239, &[b, a].concat())
+        );
+
+        // The rows of a window narrower than the area start where the pattern left off
+        let area = Rectangle::new(Point::new(318, 0), Size::new(3, 2));
+        block_on(display.write_pixels_repeating(&[a, b].concat(), area)).unwrap();
+
+        assert_eq!(
+            recorder.take(),
+            memory_write(318, 0, 319, 1, &[a, b, b, a].concat())
+        );
+    }
+
+    #[test]
+    fn write_pixels_repeating_rejects_partial_pixels() {
+        let (mut display, recorder) = initialized(Config::default());
+        let area = Rectangle::new(Point::zero(), Size::new(2, 2));
+
+        assert_eq!(
+            block_on(display.write_pixels_repeating(&[0; 3], area)),
+            Err(Error::BufferSizeMismatch {
+                expected: 4,
+                actual: 3
+            })
+        );
+        assert_eq!(recorder.take(), []);
+    }
+
+    #[test]
+    fn define_scroll_area_rejects_areas_not_adding_up_to_the_memory_rows() {
+        let (mut display, recorder) = initialized(Config::default());
+
+        assert_eq!(
+            block_on(display.define_scroll_area(20, 200, 10)),
+            Err(Error::InvalidScrollArea {
+                rows: 230,
+                height: 240
+            })
+        );
+        assert_eq!(recorder.take(), []);
+
+        block_on(display.define_scroll_area(20, 200, 20)).unwrap();
+        assert_eq!(
+            recorder.take(),
+            [command(
+                command::VERTICAL_SCROLL_DEFINE,
+                &[0, 20, 0, 200, 0, 20]
+            )]
+        );
+    }
+
+    #[test]
+    fn define_scroll_area_counts_the_memory_rows_of_the_orientation() {
+        // A 240x240 panel in the 240x320 memory of an ILI9341
+        let config = Config::builder()
+            .panel_size(240, 240)
+            .memory_size(240, 320)
+            .offset(0, 80)
+            .build()
+            .unwrap();
+        let (mut display, recorder) = initialized(config);
+
+        assert_eq!(
+            block_on(display.define_scroll_area(0, 240, 0)),
+            Err(Error::InvalidScrollArea {
+                rows: 240,
+                height: 320
+            })
+        );
+        block_on(display.define_scroll_area(80, 240, 0)).unwrap();
+        recorder.take();
+
+        // Rotated by 90 degrees the memory rows are the 240 columns
+        block_on(display.set_orientation(Orientation::new(Rotation::Deg90))).unwrap();
+        recorder.take();
+        assert_eq!(display.memory_rows(), 240);
+
+        assert_eq!(
+            block_on(display.define_scroll_area(80, 240, 0)),
+            Err(Error::InvalidScrollArea {
+                rows: 320,
+                height: 240
+            })
+        );
+        assert_eq!(recorder.take(), []);
+
+        block_on(display.define_scroll_area(0, 200, 40)).unwrap();
+        assert_eq!(
+            recorder.take(),
+            [command(
+                command::VERTICAL_SCROLL_DEFINE,
+                &[0, 0, 0, 200, 0, 40]
+            )]
+        );
+    }
+
+    #[test]
+    fn madctl_of_every_orientation() {
+        // Rotation, MADCTL without mirroring, mirrored left to right, top to bottom and both
+        let table = [
+            (Rotation::Deg0, [0x00, 0x40, 0x80, 0xc0]),
+            (Rotation::Deg90, [0x60, 0xe0, 0x20, 0xa0]),
+            (Rotation::Deg180, [0xc0, 0x80, 0x40, 0x00]),
+            (Rotation::Deg270, [0xa0, 0x20, 0xe0, 0x60]),
+        ];
+        let mirrors = [(false, false), (true, false), (false, true), (true, true)];
+
+        for (rotation, expected) in table {
+            for ((mirror_x, mirror_y), madctl) in mirrors.into_iter().zip(expected) {
+                for (color_order, bgr) in [(ColorOrder::Rgb, 0x00), (ColorOrder::Bgr, 0x08)] {
+                    let orientation = Orientation {
+                        rotation,
+                        mirror_x,
+                        mirror_y,
+                        color_order,
+                    };
+                    assert_eq!(orientation.madctl(), madctl | bgr, "{orientation:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn set_orientation_swaps_the_size_for_90_and_270_degrees() {
+        let (mut display, recorder) = initialized(Config::default());
+        assert_eq!(display.size(), Size::new(320, 240));
+
+        for (rotation, size) in [
+            (Rotation::Deg90, Size::new(240, 320)),
+            (Rotation::Deg180, Size::new(320, 240)),
+            (Rotation::Deg270, Size::new(240, 320)),
+            (Rotation::Deg0, Size::new(320, 240)),
+        ] {
+            let orientation = Orientation::new(rotation);
+            block_on(display.set_orientation(orientation)).unwrap();
+
+            assert_eq!(display.size(), size, "{rotation:?}");
+            assert_eq!(display.orientation(), orientation);
+            assert_eq!(
+                recorder.take(),
+                [command(
+                    command::MEMORY_ACCESS_CONTROL,
+                    &[orientation.madctl()]
+                )]
+            );
+        }
+    }
+
+    #[test]
+    fn display_and_idle_mode_are_only_sent_when_they_change() {
+        let (mut display, mut delay, recorder) = mock_display::<Rgb565>(Config::default());
+        block_on(display.initialize(&mut delay)).unwrap();
+        recorder.take();
+
+        block_on(display.display_on(&mut delay)).unwrap();
+        block_on(display.exit_idle_mode()).unwrap();
+        assert_eq!(recorder.take(), []);
+
+        for _ in 0..2 {
+            block_on(display.display_off(&mut delay)).unwrap();
+            block_on(display.enter_idle_mode()).unwrap();
+        }
+        assert_eq!(
+            recorder.take(),
+            [
+                command(command::DISPLAY_OFF, &[]),
+                Event::Delay { ms: 100 },
+                command(command::IDLE_MODE_ON, &[]),
+            ]
+        );
+
+        for _ in 0..2 {
+            block_on(display.display_on(&mut delay)).unwrap();
+            block_on(display.exit_idle_mode()).unwrap();
+        }
+        assert_eq!(
+            recorder.take(),
+            [
+                command(command::DISPLAY_ON, &[]),
+                Event::Delay { ms: 100 },
+                command(command::IDLE_MODE_OFF, &[]),
+            ]
+        );
+
+        // The reset of a new initialization leaves the idle mode
+        block_on(display.enter_idle_mode()).unwrap();
+        block_on(display.initialize(&mut delay)).unwrap();
+        recorder.take();
+        block_on(display.enter_idle_mode()).unwrap();
+        assert_eq!(recorder.take(), [command(command::IDLE_MODE_ON, &[])]);
+    }
+
+    #[test]
+    fn sleep_and_wake_are_only_sent_when_they_change() {
+        let (mut display, mut delay, recorder) = mock_display::<Rgb565>(Config::default());
+        block_on(display.initialize(&mut delay)).unwrap();
+        recorder.take();
+
+        block_on(display.wake(&mut delay)).unwrap();
+        assert_eq!(recorder.take(), []);
+
+        for _ in 0..2 {
+            block_on(display.sleep(&mut delay)).unwrap();
+        }
+        assert_eq!(display.sleep_mode(), ModeState::On);
+        assert_eq!(
+            recorder.take(),
+            [
+                command(command::SLEEP_MODE_ON, &[]),
+                Event::Delay { ms: 50 }
+            ]
+        );
+
+        for _ in 0..2 {
+            block_on(display.wake(&mut delay)).unwrap();
+        }
+        assert_eq!(display.sleep_mode(), ModeState::Off);
+        assert_eq!(
+            recorder.take(),
+            [
+                command(command::SLEEP_MODE_OFF, &[]),
+                Event::Delay { ms: 150 }
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_return_asleep_while_sleeping() {
+        let (mut display, mut delay, recorder) = mock_display::<Rgb565>(Config::default());
+        block_on(display.initialize(&mut delay)).unwrap();
+        block_on(display.sleep(&mut delay)).unwrap();
+        recorder.take();
+
+        let area = Rectangle::new(Point::new(10, 20), Size::new(1, 1));
+        let mut data = [0; 2];
+        let fb = Framebuffer::<Rgb565>::new(&mut data, area.size);
+        assert_eq!(
+            block_on(display.write_pixels(&[1, 2], area)),
+            Err(Error::Asleep)
+        );
+        assert_eq!(
+            block_on(display.write_pixels_repeating(&[1, 2], area)),
+            Err(Error::Asleep)
+        );
+        assert_eq!(
+            block_on(WritePixels::flush(&mut display, &fb, area.top_left)),
+            Err(Error::Asleep)
+        );
+        assert_eq!(
+            display.fill_solid(&area, Rgb565::new(31, 63, 31)),
+            Err(Error::Asleep)
+        );
+        assert_eq!(recorder.take(), []);
+
+        block_on(display.wake(&mut delay)).unwrap();
+        recorder.take();
+        block_on(display.write_pixels(&[1, 2], area)).unwrap();
+        assert_eq!(recorder.take(), memory_write(10, 20, 10, 20, &[1, 2]));
+    }
+
+    #[test]
+    fn build_checks_the_panel_against_each_axis_of_the_memory() {
+        let build = |builder: ConfigBuilder| builder.build().map(|_| ());
+
+        // A portrait panel does not fit in the landscape memory of the default
+        let portrait = || Config::builder().panel_size(240, 320);
+        assert_eq!(build(portrait()), Err(ConfigError::OutOfMemory));
+        assert_eq!(build(portrait().memory_size(240, 320)), Ok(()));
+
+        let offset = |column_offset, row_offset| {
+            Config::builder()
+                .panel_size(240, 240)
+                .memory_size(240, 320)
+                .offset(column_offset, row_offset)
+        };
+        assert_eq!(build(offset(0, 80)), Ok(()));
+        assert_eq!(build(offset(0, 81)), Err(ConfigError::OutOfMemory));
+        assert_eq!(build(offset(1, 0)), Err(ConfigError::OutOfMemory));
+    }
+
+    #[test]
+    fn set_orientation_moves_the_offset_with_the_rotation_and_mirroring() {
+        // 40 spare columns and 80 spare rows in the display memory
+        let config = Config::builder()
+            .panel_size(200, 240)
+            .memory_size(240, 320)
+            .offset(10, 20)
+            .build()
+            .unwrap();
+        let (mut display, recorder) = initialized(config);
+        let pixel = Rectangle::new(Point::zero(), Size::new(1, 1));
+
+        let mirrored = Orientation {
+            mirror_x: true,
+            ..Orientation::new(Rotation::Deg0)
+        };
+        for (orientation, x, y) in [
+            (Orientation::new(Rotation::Deg90), 20, 30),
+            (Orientation::new(Rotation::Deg180), 30, 60),
+            (Orientation::new(Rotation::Deg270), 60, 10),
+            (mirrored, 30, 20),
+            (Orientation::new(Rotation::Deg0), 10, 20),
+        ] {
+            block_on(display.set_orientation(orientation)).unwrap();
+            recorder.take();
+
+            block_on(display.write_pixels(&[1, 2], pixel)).unwrap();
+            assert_eq!(
+                recorder.take(),
+                memory_write(x, y, x, y, &[1, 2]),
+                "{orientation:?}"
+            );
+        }
+    }
+
+    /// 4x3 framebuffer where every byte is different
+    fn numbered_framebuffer(data: &mut [u8; 4 * 3 * 2]) -> Framebuffer<'_, Rgb565> {
+        let mut fb = Framebuffer::new(data, Size::new(4, 3));
+        let colors = (0..12).map(|pixel| RawU16::new(((2 * pixel) << 8) | (2 * pixel + 1)).into());
+        fb.fill_contiguous(&fb.bounding_box(), colors).unwrap();
+        fb
+    }
+
+    /// Bytes of the `rect` of the framebuffer, row after row
+    fn framebuffer_pixels(fb: &Framebuffer<'_, Rgb565>, rect: Rectangle) -> Vec<u8> {
+        let stride = fb.size().width as usize * 2;
+        let start = rect.top_left.x as usize * 2;
+        let end = start + rect.size.width as usize * 2;
+        fb.data()
+            .chunks_exact(stride)
+            .skip(rect.top_left.y as usize)
+            .take(rect.size.height as usize)
+            .flat_map(|row| &row[start..end])
+            .copied()
+            .collect()
+    }
+
+    #[test]
+    fn flush_sends_the_part_of_the_framebuffer_on_the_panel() {
+        let (mut display, recorder) = initialized(Config::default());
+        let mut data = [0; 4 * 3 * 2];
+        let fb = numbered_framebuffer(&mut data);
+
+        // The left column and the bottom row are off the panel
+        block_on(WritePixels::flush(&mut display, &fb, Point::new(-1, 238))).unwrap();
+        let visible = Rectangle::new(Point::new(1, 0), Size::new(3, 2));
+        assert_eq!(
+            recorder.take(),
+            memory_write(0, 238, 2, 239, &framebuffer_pixels(&fb, visible))
+        );
+
+        // The right two columns and the top row are off the panel
+        block_on(WritePixels::flush(&mut display, &fb, Point::new(318, -1))).unwrap();
+        let visible = Rectangle::new(Point::new(0, 1), Size::new(2, 2));
+        assert_eq!(
+            recorder.take(),
+            memory_write(318, 0, 319, 1, &framebuffer_pixels(&fb, visible))
+        );
+    }
+
+    #[test]
+    fn flush_of_a_framebuffer_off_the_panel_sends_nothing() {
+        let (mut display, recorder) = initialized(Config::default());
+        let mut data = [0; 4 * 3 * 2];
+        let fb = numbered_framebuffer(&mut data);
+
+        for top_left in [Point::new(320, 0), Point::new(-4, 0), Point::new(0, 240)] {
+            block_on(WritePixels::flush(&mut display, &fb, top_left)).unwrap();
+            assert_eq!(recorder.take(), [], "{top_left:?}");
+        }
+    }
+
+    #[test]
+    fn flush_region_sends_the_part_of_src_inside_the_framebuffer() {
+        let (mut display, recorder) = initialized(Config::default());
+        let mut data = [0; 4 * 3 * 2];
+        let fb = numbered_framebuffer(&mut data);
+
+        // Past the bottom right corner of the framebuffer
+        let src = Rectangle::new(Point::new(2, 1), Size::new(4, 4));
+        block_on(display.flush_region(&fb, src, Point::new(10, 20))).unwrap();
+        let region = Rectangle::new(Point::new(2, 1), Size::new(2, 2));
+        assert_eq!(
+            recorder.take(),
+            memory_write(10, 20, 11, 21, &framebuffer_pixels(&fb, region))
+        );
+
+        // Before the top left corner of the framebuffer, the pixels keep their place
+        let src = Rectangle::new(Point::new(-1, -1), Size::new(2, 2));
+        block_on(display.flush_region(&fb, src, Point::new(10, 20))).unwrap();
+        let region = Rectangle::new(Point::zero(), Size::new(1, 1));
+        assert_eq!(
+            recorder.take(),
+            memory_write(11, 21, 11, 21, &framebuffer_pixels(&fb, region))
+        );
+
+        // Outside of the framebuffer
+        let src = Rectangle::new(Point::new(4, 0), Size::new(2, 2));
+        block_on(display.flush_region(&fb, src, Point::new(10, 20))).unwrap();
+        assert_eq!(recorder.take(), []);
+    }
+
+    #[test]
+    fn flush_region_sends_the_part_of_src_on_the_panel() {
+        let (mut display, recorder) = initialized(Config::default());
+        let mut data = [0; 4 * 3 * 2];
+        let fb = numbered_framebuffer(&mut data);
+
+        let src = Rectangle::new(Point::new(1, 0), Size::new(3, 3));
+        block_on(display.flush_region(&fb, src, Point::new(318, 238))).unwrap();
+        let region = Rectangle::new(Point::new(1, 0), Size::new(2, 2));
+        assert_eq!(
+            recorder.take(),
+            memory_write(318, 238, 319, 239, &framebuffer_pixels(&fb, region))
+        );
+
+        block_on(display.flush_region(&fb, src, Point::new(320, 0))).unwrap();
+        assert_eq!(recorder.take(), []);
+    }
+
+    #[test]
+    fn flush_region_skips_the_stride_of_a_wider_framebuffer() {
+        // 300x3 strip, the pixel at (x, y) has the raw color 1000 * y + x
+        const WIDTH: usize = 300;
+        let mut data = [0; WIDTH * 3 * 2];
+        let mut fb = Framebuffer::<Rgb565>::new(&mut data, Size::new(WIDTH as u32, 3));
+        let colors =
+            (0..3 * WIDTH).map(|i| RawU16::new((1000 * (i / WIDTH) + i % WIDTH) as u16).into());
+        fb.fill_contiguous(&fb.bounding_box(), colors).unwrap();
+        let (mut display, recorder) = initialized(Config::default());
+
+        let pixels = |x0: u16, x1: u16| -> Vec<u8> {
+            (0..3)
+                .flat_map(|y| (x0..=x1).flat_map(move |x| (1000 * y + x).to_be_bytes()))
+                .collect()
+        };
+
+        // A digit fits in the stack buffer, its rows are sent together
+        let digit = Rectangle::new(Point::new(100, 0), Size::new(16, 3));
+        block_on(display.flush_region(&fb, digit, Point::new(50, 10))).unwrap();
+        assert_eq!(
+            recorder.take(),
+            memory_write(50, 10, 65, 12, &pixels(100, 115))
+        );
+
+        // Rows longer than the stack buffer are sent from the framebuffer
+        let wide = Rectangle::new(Point::new(10, 0), Size::new(280, 3));
+        block_on(display.flush_region(&fb, wide, Point::new(0, 100))).unwrap();
+        assert_eq!(
+            recorder.take(),
+            memory_write(0, 100, 279, 102, &pixels(10, 289))
+        );
+    }
+
+    /// Display using the default implementations of [`WritePixels`]
+    struct RowRecorder(Vec<(Rectangle, Vec<u8>)>);
+
+    impl WritePixels for RowRecorder {
+        type Error = ();
+        type Color = Rgb666;
+
+        async fn write_pixels(&mut self, data: &[u8], area: Rectangle) -> Result<(), ()> {
+            self.0.push((area, data.to_vec()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn default_flush_region_uses_the_bytes_per_pixel_of_the_color() {
+        // 3x2 pixels of 3 bytes, the color of a pixel is its index
+        let mut data = [0; 3 * 2 * 3];
+        let mut fb = Framebuffer::<Rgb666>::new(&mut data, Size::new(3, 2));
+        let colors = (0..6).map(|pixel| Rgb666::new(pixel, pixel, pixel));
+        fb.fill_contiguous(&fb.bounding_box(), colors).unwrap();
+        let mut display = RowRecorder(Vec::new());
+
+        let src = Rectangle::new(Point::new(1, 0), Size::new(2, 2));
+        block_on(display.flush_region(&fb, src, Point::new(10, 20))).unwrap();
+
+        let row = |y, pixels: [u8; 2]| {
+            (
+                Rectangle::new(Point::new(10, y), Size::new(2, 1)),
+                pixels.iter().flat_map(|pixel| [*pixel << 2; 3]).collect(),
+            )
+        };
+        assert_eq!(display.0, [row(20, [1, 2]), row(21, [4, 5])]);
     }
 }
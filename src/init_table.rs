@@ -0,0 +1,82 @@
+use crate::command;
+
+/// One step of an initialization sequence, a command with its parameters and the
+/// time to wait after it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InitCommand {
+    /// Command sent to the display.
+    pub command: u8,
+    /// Parameters sent after the command.
+    pub params: &'static [u8],
+    /// Time to wait after the command in milliseconds.
+    pub delay_ms: u32,
+}
+
+impl InitCommand {
+    /// Creates a step that does not wait after the command
+    pub const fn new(command: u8, params: &'static [u8]) -> Self {
+        Self {
+            command,
+            params,
+            delay_ms: 0,
+        }
+    }
+
+    /// Wait `delay_ms` milliseconds after the command
+    pub const fn with_delay(self, delay_ms: u32) -> Self {
+        Self { delay_ms, ..self }
+    }
+}
+
+/// Power, timing and gamma settings recommended by the panel vendor.
+///
+/// The gamma curves are not part of the table, they are sent afterwards so they
+/// can be replaced, see [`GammaCurve`].
+pub const VENDOR_INIT: &[InitCommand] = &[
+    InitCommand::new(command::POWER_B, &[0x00, 0xc1, 0x30]),
+    InitCommand::new(command::POWER_SEQ, &[0x64, 0x03, 0x12, 0x81]),
+    InitCommand::new(command::DRIVER_TIMING_CONTROL_A, &[0x85, 0x00, 0x78]),
+    InitCommand::new(command::POWER_A, &[0x39, 0x2c, 0x00, 0x34, 0x02]),
+    InitCommand::new(command::PUMP_RATIO_CONTROL, &[0x20]),
+    InitCommand::new(command::DRIVER_TIMING_CONTROL_B, &[0x00, 0x00]),
+    // GVDD = 4.6V
+    InitCommand::new(command::POWER_CONTROL_1, &[0x23]),
+    InitCommand::new(command::POWER_CONTROL_2, &[0x10]),
+    // VCOMH = 4.25V, VCOML = -1.5V
+    InitCommand::new(command::VCOM_CONTROL_1, &[0x3e, 0x28]),
+    InitCommand::new(command::VCOM_CONTROL_2, &[0x86]),
+    // 79Hz
+    InitCommand::new(command::FRAME_RATE_CONTROL_1, &[0x00, 0x18]),
+    InitCommand::new(command::DISPLAY_FUNCTION_CONTROL, &[0x08, 0x82, 0x27]),
+    // Disable the 3 gamma control, the curves below are used
+    InitCommand::new(command::ENABLE_3G, &[0x00]),
+    InitCommand::new(command::GAMMA_SET, &[0x01]),
+];
+
+/// Positive and negative gamma correction curves of the panel.
+///
+/// Each curve has the 15 parameters of the gamma correction commands, see the
+/// ILI9341 datasheet for their meaning.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GammaCurve {
+    /// Parameters of the positive gamma correction command.
+    pub positive: [u8; 15],
+    /// Parameters of the negative gamma correction command.
+    pub negative: [u8; 15],
+}
+
+impl Default for GammaCurve {
+    fn default() -> Self {
+        VENDOR_GAMMA
+    }
+}
+
+/// Gamma curves recommended by the panel vendor
+pub const VENDOR_GAMMA: GammaCurve = GammaCurve {
+    positive: [
+        0x0f, 0x31, 0x2b, 0x0c, 0x0e, 0x08, 0x4e, 0xf1, 0x37, 0x07, 0x10, 0x03, 0x0e, 0x09, 0x00,
+    ],
+    negative: [
+        0x00, 0x0e, 0x14, 0x03, 0x11, 0x07, 0x31, 0xc1, 0x48, 0x08, 0x0f, 0x0c, 0x31, 0x36, 0x0f,
+    ],
+};
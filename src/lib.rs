@@ -0,0 +1,31 @@
+//! Drivers and application logic of the M5Stack environment monitor.
+//!
+//! Everything except the tasks and the hardware setup of the firmware lives in
+//! this library, which only depends on the `embedded-hal` traits so it also
+//! builds on the host, e.g. to run the tests with `cargo +stable test-host`.
+
+#![cfg_attr(not(test), no_std)]
+
+pub mod aht20;
+pub mod alarm;
+pub mod backlight;
+pub mod bmp280;
+pub mod command;
+pub mod compositor;
+pub mod console;
+pub mod dht12;
+pub mod double_buffer;
+pub mod framebuffer;
+pub mod graph;
+pub mod ili9341_async;
+pub mod init_table;
+pub mod pixel_format;
+pub mod psychrometrics;
+pub mod sensor;
+pub mod sht3x;
+pub mod statistics;
+pub mod tiled;
+pub mod widget;
+
+#[cfg(test)]
+mod test_util;
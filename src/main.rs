@@ -24,22 +24,16 @@ App/part. size:    125,168/4,128,768 bytes, 3.03%
 #![no_std]
 #![no_main]
 
-pub mod command;
-pub mod framebuffer;
-pub mod ili9341_async;
-
 use core::fmt::Write;
 use heapless::String;
 use log::*;
 
 use embedded_graphics::{
-    mono_font::{ascii::FONT_10X20, ascii::FONT_8X13_BOLD, MonoTextStyleBuilder},
+    mono_font::{ascii::FONT_10X20, ascii::FONT_6X10, ascii::FONT_8X13_BOLD, MonoTextStyleBuilder},
     pixelcolor::Rgb565,
     prelude::*,
-    primitives::{PrimitiveStyle, PrimitiveStyleBuilder, Rectangle, RoundedRectangle},
-    text::{Alignment, Baseline, Text, TextStyleBuilder},
+    primitives::Rectangle,
 };
-
 use profont::PROFONT_24_POINT;
 
 use esp_backtrace as _;
@@ -61,62 +55,203 @@ use esp_hal::{
 use embassy_executor::Spawner;
 use embassy_sync::blocking_mutex::raw::NoopRawMutex;
 use embassy_sync::channel::{Channel, Receiver, Sender};
-use embassy_time::{Delay, Duration, Instant, Timer};
+use embassy_sync::mutex::Mutex;
+use embassy_sync::pubsub::{PubSubChannel, Publisher, Subscriber, WaitResult};
+use embassy_time::{with_timeout, Delay, Duration, Instant, Timer};
 
 use static_cell::ConstStaticCell;
 use static_cell::StaticCell;
 
 use embedded_hal_bus::spi::ExclusiveDevice;
 
-use crate::framebuffer::Framebuffer;
-use crate::ili9341_async::{Config, Ili9341, WritePixels};
+use embassy_embedded_graphics_dht12::alarm::{
+    AlarmConfig, AlarmEvent, AlarmMonitor, Quantity, ThresholdConfig,
+};
+use embassy_embedded_graphics_dht12::backlight::{AutoDim, PinBacklight, MAX_BRIGHTNESS};
+use embassy_embedded_graphics_dht12::compositor::Compositor;
+use embassy_embedded_graphics_dht12::console::Console;
+use embassy_embedded_graphics_dht12::double_buffer::{
+    DoubleBuffer, FlushChannels, Flusher, BYTES_PER_PIXEL,
+};
+use embassy_embedded_graphics_dht12::graph::{HistoryGraph, HistoryView};
+use embassy_embedded_graphics_dht12::ili9341_async::{
+    Config, Ili9341, InitSequence, ModeState, Rotation,
+};
+use embassy_embedded_graphics_dht12::psychrometrics::{DerivedMetric, Psychrometrics};
+use embassy_embedded_graphics_dht12::sensor::{
+    AnySensor, EnvironmentSensor, RetryConfig, Sampler, SensorStatus, TemperatureUnit,
+};
+use embassy_embedded_graphics_dht12::statistics::{EnvironmentStatistics, StatisticsWindow};
+use embassy_embedded_graphics_dht12::tiled::TiledDisplay;
+use embassy_embedded_graphics_dht12::widget::{Label, Panel, Title, ValueField};
 
 type DisplaySpi =
     ExclusiveDevice<SpiDmaBus<'static, SPI2, FullDuplexMode, Async>, Output<'static>, Delay>;
 
-pub struct Dht12Reading {
-    pub humidity: f32,
-    pub temp_fahrenheit: f32,
-}
+type Display = Ili9341<DisplaySpi, Output<'static>, Output<'static>, PinBacklight<Output<'static>>>;
+
+type Sensor = AnySensor<I2c<'static, I2C0, Async>, Delay>;
+
+/// SPI clock of the display in kHz
+const DISPLAY_SPI_KHZ: u32 = 10_000;
+
+/// SPI clock used while reading from the display in kHz, it reads at most at about 6.6MHz
+const DISPLAY_READ_SPI_KHZ: u32 = 6_000;
 
-/// Period to wait between DHT12 readings
+/// Unit the temperature is shown in on the display
+const TEMPERATURE_UNIT: TemperatureUnit = TemperatureUnit::Fahrenheit;
+
+/// Derived metrics shown in the row between the temperature and humidity panels,
+/// leave empty to hide the row
+const DERIVED_METRICS: &[DerivedMetric] = &[
+    DerivedMetric::DewPoint,
+    DerivedMetric::HeatIndex,
+    DerivedMetric::Humidex,
+    DerivedMetric::AbsoluteHumidity,
+];
+
+/// Period to wait between sensor readings
 const SAMPLING_PERIOD: Duration = Duration::from_secs(2);
 
-/// A channel between read_dht12_task and render task
-static CHANNEL: StaticCell<Channel<NoopRawMutex, Dht12Reading, 2>> = StaticCell::new();
+/// Retries used when a sensor reading fails before reporting a sensor fault
+const RETRY_CONFIG: RetryConfig = RetryConfig {
+    max_retries: 3,
+    initial_backoff_ms: 50,
+    max_backoff_ms: 400,
+};
+
+/// A channel between read_sensor_task and render task
+static CHANNEL: StaticCell<Channel<NoopRawMutex, SensorStatus, 2>> = StaticCell::new();
+
+/// Temperature alarms in tenths of a degree celsius and humidity alarms in tenths of a percent
+const ALARM_CONFIG: AlarmConfig = AlarmConfig {
+    temperature: ThresholdConfig {
+        low: Some(150),
+        high: Some(300),
+        hysteresis: 5,
+        min_duration_secs: 10,
+    },
+    humidity: ThresholdConfig {
+        low: Some(200),
+        high: Some(700),
+        hysteresis: 20,
+        min_duration_secs: 10,
+    },
+};
+
+/// Number of alarm events kept for subscribers that have not read them yet
+const ALARM_EVENT_CAPACITY: usize = 4;
+
+/// Number of tasks that can subscribe to the alarm events, e.g. a logger and a buzzer
+const ALARM_SUBSCRIBERS: usize = 2;
+
+type AlarmChannel =
+    PubSubChannel<NoopRawMutex, AlarmEvent, ALARM_EVENT_CAPACITY, ALARM_SUBSCRIBERS, 1>;
+type AlarmPublisher =
+    Publisher<'static, NoopRawMutex, AlarmEvent, ALARM_EVENT_CAPACITY, ALARM_SUBSCRIBERS, 1>;
+type AlarmSubscriber =
+    Subscriber<'static, NoopRawMutex, AlarmEvent, ALARM_EVENT_CAPACITY, ALARM_SUBSCRIBERS, 1>;
+
+/// A channel the render task publishes alarm events on for the other tasks
+static ALARM_CHANNEL: StaticCell<AlarmChannel> = StaticCell::new();
+
+/// Time a panel with a raised alarm shows the alarm color and the normal color
+const ALARM_BLINK_PERIOD: Duration = Duration::from_millis(500);
+
+/// Time without a raised alarm before the backlight is dimmed, in milliseconds
+const AUTO_DIM_TIMEOUT_MS: u64 = 5 * 60 * 1000;
+
+/// Brightness of the dimmed backlight, the backlight on a pin can only be switched off
+const DIMMED_BRIGHTNESS: u8 = 0;
+
+/// Duration of a backlight fade in milliseconds
+const BACKLIGHT_FADE_MS: u32 = 300;
+
+const TEMPERATURE_PANEL_COLOR: Rgb565 = Rgb565::GREEN;
+const HUMIDITY_PANEL_COLOR: Rgb565 = Rgb565::YELLOW;
+const ALARM_PANEL_COLOR: Rgb565 = Rgb565::RED;
+
+/// Row of the screen title, the full screen width so the title is centered
+const TITLE_AREA: Rectangle = Rectangle::new(Point::new(0, 10), Size::new(320, 20));
+
+/// Number of characters in the title, using font 10x20 the screen is 32 characters wide
+const TITLE_CHARS: usize = 32;
+
+const TEMPERATURE_PANEL_AREA: Rectangle = Rectangle::new(Point::new(60, 40), Size::new(200, 30));
+const HUMIDITY_PANEL_AREA: Rectangle = Rectangle::new(Point::new(60, 90), Size::new(200, 30));
+
+/// Number of characters in a panel value, e.g. "100%" or "-40F"
+const PANEL_VALUE_CHARS: usize = 4;
+
+/// Rolling temperature and humidity statistics, kept in a static as they are too large for the task
+static STATISTICS: StaticCell<EnvironmentStatistics> = StaticCell::new();
+
+/// History of the readings shown between the humidity panel and the time
+const HISTORY_VIEW: HistoryView = HistoryView::Graph;
+
+/// First row of the history
+const HISTORY_TOP: u16 = 122;
+
+/// Height of the history graph in pixels
+const GRAPH_HEIGHT: u32 = 36;
+
+/// Number of lines in the reading log, using font 6x10 the log covers the rows of the graph
+const LOG_LINES: u16 = 3;
+
+/// Number of samples in the history graph, the graph is this plus 48 pixels for the labels wide
+const GRAPH_SAMPLES: usize = 272;
+
+/// History graph of the last readings, kept in a static as it is too large for the task
+static HISTORY_GRAPH: StaticCell<HistoryGraph<GRAPH_SAMPLES>> = StaticCell::new();
+
+/// Number of characters in a row of small text, using font 6x10 a row is 51x6=306 pixels wide
+const SMALL_TEXT_ROW_CHARS: usize = 51;
+
+/// Number of separate areas of the screen the compositor keeps track of before merging them
+const DIRTY_RECTANGLES: usize = 8;
 
 /// Frame Buffer Size = display width x 1/4 Display height x number of bytes in pexel color
-const FRAME_BUFFER_SIZE: usize = 320 * 60 * 2;
+///
+/// The pixel data is split in two halves of 1/8 display height, one is drawn while
+/// the other one is sent to the display.
+const FRAME_BUFFER_SIZE: usize = 320 * 60 * BYTES_PER_PIXEL;
 
 /// Create static pixel data buffer can be used by both sync and async
 static PIXEL_DATA: ConstStaticCell<[u8; FRAME_BUFFER_SIZE]> =
     ConstStaticCell::new([0; FRAME_BUFFER_SIZE]);
 
+/// Channels passing the halves of the pixel data between render_task and flush_task
+static FLUSH_CHANNELS: StaticCell<FlushChannels> = StaticCell::new();
+
+/// The display is shared by flush_task, sending the pixels, and render_task, sending commands
+static DISPLAY: StaticCell<Mutex<NoopRawMutex, Display>> = StaticCell::new();
+
 #[embassy_executor::task]
 async fn render_task(
-    mut display: Ili9341<DisplaySpi, Output<'static>, Output<'static>, Output<'static>>,
-    receiver: Receiver<'static, NoopRawMutex, Dht12Reading, 2>,
+    display: &'static Mutex<NoopRawMutex, Display>,
+    mut double_buffer: DoubleBuffer,
+    receiver: Receiver<'static, NoopRawMutex, SensorStatus, 2>,
+    alarm_publisher: AlarmPublisher,
+    sensor_name: &'static str,
+    unit: TemperatureUnit,
 ) {
-    let pixel_data = PIXEL_DATA.take();
-    let mut fb = Framebuffer::<Rgb565>::new(pixel_data, Size::new(320, 60));
-
-    // Clear the screen - break up screen into 4 rectangle shapes the size
-    // 320 x 60 which is the same size as frame buffer
-    Rectangle::new(Point::new(0, 0), Size::new(320, 60))
-        .into_styled(PrimitiveStyle::with_fill(Rgb565::BLACK))
-        .draw(&mut fb)
-        .unwrap();
-
+    // Clear the screen, the tiled display draws it in strips the size of half the frame buffer
     let mut start = Instant::now();
-    for i in 0..4 {
-        display.flush(&fb, Point::new(0, i * 60)).await;
-    }
+    TiledDisplay::new(&mut double_buffer, Size::new(320, 240))
+        .draw(|target| {
+            // Drawing in a tile cannot fail
+            let _ = target.clear(Rgb565::BLACK);
+        })
+        .await;
+    double_buffer.wait_idle().await;
     info!(
         "clear display: {} milliseconds",
         Instant::now().duration_since(start).as_millis()
     );
 
-    display.turn_on_backlight().unwrap();
+    if let Err(error) = display.lock().await.turn_on_backlight() {
+        error!("Failed to turn on the backlight: {:?}", error);
+    }
 
     // Create character styles
     let char_10x20_blue_style = MonoTextStyleBuilder::new()
@@ -126,230 +261,448 @@ async fn render_task(
         .background_color(Rgb565::BLACK)
         .build();
 
-    // Create a new title style
-    let text_style = TextStyleBuilder::new()
-        .baseline(Baseline::Bottom)
-        .alignment(Alignment::Left)
-        .build();
-
-    // Create screen title
-    start = Instant::now();
-    fb = Framebuffer::<Rgb565>::new(pixel_data, Size::new(170, 20));
-    Text::with_text_style(
-        "DHT12 SENSOR DATA",
-        Point::new(0, 19),
-        char_10x20_blue_style,
-        text_style,
-    )
-    .draw(&mut fb)
-    .unwrap();
-    display.flush(&fb, Point::new(75, 10)).await;
-
-    warn!(
-        "Display Title: {} microseconds",
-        Instant::now().duration_since(start).as_micros()
-    );
-
-    // Temperature round rectangle style
-    let style_rect_temperature = PrimitiveStyleBuilder::new()
-        .fill_color(Rgb565::GREEN)
+    let time_style = MonoTextStyleBuilder::new()
+        .font(&PROFONT_24_POINT)
+        .text_color(Rgb565::RED)
+        // Need this so previous text is erased
+        .background_color(Rgb565::BLACK)
         .build();
 
-    start = Instant::now();
-    fb = Framebuffer::<Rgb565>::new(pixel_data, Size::new(200, 30));
-    RoundedRectangle::with_equal_corners(
-        Rectangle::new(Point::new(0, 0), Size::new(200, 30)),
-        Size::new(10, 10),
-    )
-    .into_styled(style_rect_temperature)
-    .draw(&mut fb)
-    .unwrap();
-
-    display.flush(&fb, Point::new(60, 40)).await;
-    warn!(
-        "rounded rectangle: {} microseconds",
-        Instant::now().duration_since(start).as_micros()
-    );
-
-    // Humidity round rectangle style
-    let style_rect_humidity = PrimitiveStyleBuilder::new()
-        .fill_color(Rgb565::YELLOW)
+    let sensor_error_style = MonoTextStyleBuilder::new()
+        .font(&FONT_10X20)
+        .text_color(Rgb565::RED)
+        // Need this so previous text is erased
+        .background_color(Rgb565::BLACK)
         .build();
 
-    start = Instant::now();
-    fb = Framebuffer::<Rgb565>::new(pixel_data, Size::new(200, 30));
-    RoundedRectangle::with_equal_corners(
-        Rectangle::new(Point::new(0, 0), Size::new(200, 30)),
-        Size::new(10, 10),
-    )
-    .into_styled(style_rect_humidity)
-    .draw(&mut fb)
-    .unwrap();
-
-    display.flush(&fb, Point::new(60, 90)).await;
-    warn!(
-        "rounded rectangle: {} microseconds",
-        Instant::now().duration_since(start).as_micros()
-    );
-
-    let temp_char_style = MonoTextStyleBuilder::new()
+    let pressure_char_style = MonoTextStyleBuilder::new()
         .font(&FONT_8X13_BOLD)
-        .text_color(Rgb565::BLACK)
+        .text_color(Rgb565::WHITE)
         // Need this so previous text is erased
-        .background_color(Rgb565::GREEN)
+        .background_color(Rgb565::BLACK)
         .build();
 
-    // Create temperature title
-    // Using font 8x13: Temperature is 11 characters 11x8=88 wide by 13 high so framebuffer will be 88, 13
-    fb = Framebuffer::<Rgb565>::new(pixel_data, Size::new(88, 13));
-    Text::with_text_style(
-        "Temperature",
-        Point::new(0, 12),
-        temp_char_style,
-        text_style,
-    )
-    .draw(&mut fb)
-    .unwrap();
-    display.flush(&fb, Point::new(76, 50)).await;
-
-    let humidity_char_style = MonoTextStyleBuilder::new()
-        .font(&FONT_8X13_BOLD)
-        .text_color(Rgb565::BLACK)
+    let small_char_style = MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(Rgb565::WHITE)
         // Need this so previous text is erased
-        .background_color(Rgb565::YELLOW)
+        .background_color(Rgb565::BLACK)
         .build();
 
-    // Create Humidity title
-    // Using font 8x13: Humidity is 8 characters 8x8=64 wide by 13 high so framebuffer will be 88, 13
-    fb = Framebuffer::<Rgb565>::new(pixel_data, Size::new(64, 13));
-    Text::with_text_style(
+    // Create the screen layout
+    let mut title_str = String::<TITLE_CHARS>::new();
+    let _ = write!(title_str, "{sensor_name} SENSOR DATA");
+    let mut title = Title::<TITLE_CHARS>::new(TITLE_AREA, &title_str, char_10x20_blue_style);
+
+    let mut temperature_panel = Panel::<PANEL_VALUE_CHARS>::new(
+        TEMPERATURE_PANEL_AREA,
+        "Temperature",
+        &FONT_8X13_BOLD,
+        TEMPERATURE_PANEL_COLOR,
+    );
+    let mut humidity_panel = Panel::<PANEL_VALUE_CHARS>::new(
+        HUMIDITY_PANEL_AREA,
         "Humidity",
-        Point::new(0, 12),
-        humidity_char_style,
-        text_style,
-    )
-    .draw(&mut fb)
-    .unwrap();
-    display.flush(&fb, Point::new(76, 100)).await;
+        &FONT_8X13_BOLD,
+        HUMIDITY_PANEL_COLOR,
+    );
 
-    let time_style = MonoTextStyleBuilder::new()
-        .font(&PROFONT_24_POINT)
-        .text_color(Rgb565::RED)
-        // Need this so previous text is erased
-        .background_color(Rgb565::BLACK)
-        .build();
+    let mut derived_metrics_row =
+        ValueField::<SMALL_TEXT_ROW_CHARS>::new(Point::new(7, 75), small_char_style);
 
-    // Create time value
-    fb = Framebuffer::<Rgb565>::new(pixel_data, Size::new(128, 25));
-    Text::with_text_style("12:00 pm", Point::new(0, 24), time_style, text_style)
-        .draw(&mut fb)
-        .unwrap();
-    display.flush(&fb, Point::new(96, 160)).await;
+    let mut time = Label::new(Point::new(96, 160), "12:00 pm", time_style);
+
+    // Only sensors with pressure show the pressure left and right of the time,
+    // the label is set with the first pressure
+    let mut pressure_label = ValueField::<8>::new(Point::new(8, 166), pressure_char_style);
+    let mut pressure_field = ValueField::<7>::new(Point::new(240, 166), pressure_char_style);
 
-    let mut old_humidity_value: i8 = -30;
-    let mut old_temperature_value: i8 = -100;
-    let value_font_width = 8;
+    // Shown below the time while the sensor is faulty
+    let mut sensor_error = ValueField::<12>::new(Point::new(100, 190), sensor_error_style);
+
+    let graph = HISTORY_GRAPH.init(HistoryGraph::new(
+        Point::new(0, HISTORY_TOP as i32),
+        GRAPH_HEIGHT,
+        unit,
+    ));
+    let mut reading_log = Console::new(HISTORY_TOP, LOG_LINES, small_char_style);
+    if HISTORY_VIEW == HistoryView::Log {
+        if let Err(error) = reading_log.setup(&mut *display.lock().await).await {
+            error!("Failed to set up the reading log: {:?}", error);
+        }
+    }
+
+    let statistics = STATISTICS.init(EnvironmentStatistics::new());
+    let mut hour_statistics_row =
+        ValueField::<SMALL_TEXT_ROW_CHARS>::new(Point::new(7, 214), small_char_style);
+    let mut day_statistics_row =
+        ValueField::<SMALL_TEXT_ROW_CHARS>::new(Point::new(7, 226), small_char_style);
+
+    let mut compositor = Compositor::<DIRTY_RECTANGLES>::new(Size::new(320, 240));
+
+    let mut alarms = AlarmMonitor::new(ALARM_CONFIG);
+    let mut blink_on = false;
+    let mut next_blink = Instant::now() + ALARM_BLINK_PERIOD;
+
+    let mut auto_dim = AutoDim::new(AUTO_DIM_TIMEOUT_MS, MAX_BRIGHTNESS, DIMMED_BRIGHTNESS);
 
     loop {
-        let dht12_reading = receiver.receive().await;
-        let humidity: i8 = dht12_reading.humidity as i8;
-        let temperature: i8 = dht12_reading.temp_fahrenheit as i8;
+        // Dim the backlight after a while without alarms, a raised alarm lights it up again
+        let now_ms = Instant::now().as_millis();
+        let brightness =
+            if alarms.is_active(Quantity::Temperature) || alarms.is_active(Quantity::Humidity) {
+                auto_dim.activity(now_ms)
+            } else {
+                auto_dim.poll(now_ms)
+            };
+        if let Some(brightness) = brightness {
+            if let Err(error) = display
+                .lock()
+                .await
+                .fade_brightness(brightness, BACKLIGHT_FADE_MS, &mut Delay)
+                .await
+            {
+                error!("Failed to fade the backlight: {:?}", error);
+            }
+        }
+
+        // The panels of raised alarms blink
+        let panel_color = |quantity, color| {
+            if blink_on && alarms.is_active(quantity) {
+                ALARM_PANEL_COLOR
+            } else {
+                color
+            }
+        };
+        temperature_panel.set_color(panel_color(Quantity::Temperature, TEMPERATURE_PANEL_COLOR));
+        humidity_panel.set_color(panel_color(Quantity::Humidity, HUMIDITY_PANEL_COLOR));
+
+        // Redraw what changed since the last pass, the first pass draws the whole layout
+        start = Instant::now();
+        compositor
+            .render(
+                &mut double_buffer,
+                &mut [
+                    &mut title,
+                    &mut temperature_panel,
+                    &mut derived_metrics_row,
+                    &mut humidity_panel,
+                    &mut time,
+                    &mut pressure_label,
+                    &mut pressure_field,
+                    &mut sensor_error,
+                    &mut hour_statistics_row,
+                    &mut day_statistics_row,
+                ],
+            )
+            .await;
+        debug!(
+            "compositor: {} microseconds",
+            Instant::now().duration_since(start).as_micros()
+        );
+
+        // Wait for the next reading, toggling the blinking panels while waiting
+        let timeout = next_blink.saturating_duration_since(Instant::now());
+        let Ok(status) = with_timeout(timeout, receiver.receive()).await else {
+            blink_on = !blink_on;
+            next_blink = Instant::now() + ALARM_BLINK_PERIOD;
+            continue;
+        };
+
+        let reading = match status {
+            SensorStatus::Reading(reading) => reading,
+
+            SensorStatus::Fault {
+                error,
+                consecutive_failures,
+            } => {
+                warn!(
+                    "SENSOR ERROR = {:?}   CONSECUTIVE FAILURES = {:?}",
+                    error, consecutive_failures
+                );
+
+                sensor_error.set("SENSOR ERROR");
+                continue;
+            }
+        };
+
+        // Erase the sensor error
+        sensor_error.set("");
+
+        let now_secs = Instant::now().as_secs();
+        statistics.push(now_secs, &reading);
+
+        // Evaluate the alarms and publish the changes to the other tasks
+        for event in alarms.update(now_secs, &reading) {
+            alarm_publisher.publish_immediate(event);
+
+            // Show a raised alarm right away instead of at the next blink
+            if let AlarmEvent::Raised { .. } = event {
+                blink_on = true;
+                next_blink = Instant::now() + ALARM_BLINK_PERIOD;
+            }
+        }
+
+        let humidity: Option<i8> = reading.humidity().map(|h| h as i8);
+        let temperature: i16 = reading.temperature(unit) as i16;
+        let pressure: Option<u16> = reading.pressure_hpa().map(|p| p as u16);
 
         info!(
-            "HUMIDITY = {:?}   TEMPERATURE F = {:?}",
-            humidity, temperature
+            "HUMIDITY = {:?}   TEMPERATURE {} = {:?}   PRESSURE = {:?}",
+            humidity,
+            unit.symbol(),
+            temperature,
+            pressure
         );
 
-        // Update display if temperature value changed
-        if temperature != old_temperature_value {
-            old_temperature_value = temperature;
+        // Widgets are only redrawn by the compositor when their value changed
+        temperature_panel.set_value(format_args!("{temperature}{}", unit.symbol()));
 
-            let mut temperature_value_str = String::<8>::new();
-            let _ = write!(temperature_value_str, "{temperature}F");
+        // Sensors without humidity show dashes
+        match humidity {
+            Some(humidity) => humidity_panel.set_value(format_args!("{humidity}%")),
+            None => humidity_panel.set_value(format_args!("--%")),
+        }
 
-            // Count the number of characters in temperaturte value string to determine framebuffer width
-            let temp_pixel_width =
-                (temperature_value_str.chars().count() * value_font_width) as u32;
+        if let Some(pressure) = pressure {
+            // Pad to 4 digits so the unit stays in place
+            pressure_label.set("Pressure");
+            pressure_field.set_fmt(format_args!("{pressure:>4}hPa"));
+        }
 
-            // Create temperature value
-            fb = Framebuffer::<Rgb565>::new(pixel_data, Size::new(temp_pixel_width, 13));
-            Text::with_text_style(
-                &temperature_value_str,
-                Point::new(0, 12),
-                temp_char_style,
-                text_style,
-            )
-            .draw(&mut fb)
-            .unwrap();
-            display.flush(&fb, Point::new(220, 50)).await;
+        // Sensors without humidity have no derived metrics, the row stays empty
+        if !DERIVED_METRICS.is_empty() {
+            match Psychrometrics::from_reading(&reading) {
+                Some(psychrometrics) => {
+                    derived_metrics_row.set(&format_derived_metrics(&psychrometrics, unit))
+                }
+                None => derived_metrics_row.set(""),
+            }
+        }
+
+        // Scroll the history, the log only sends the new line to the display
+        match HISTORY_VIEW {
+            HistoryView::Graph => {
+                graph.push(&reading);
+                graph.render(&mut double_buffer).await;
+            }
+
+            HistoryView::Log => {
+                let line = format_log_line(now_secs, temperature, humidity, pressure, unit);
+                // A lost line only leaves a gap in the log
+                if let Err(error) = reading_log
+                    .push_line(&mut *display.lock().await, &line)
+                    .await
+                {
+                    error!("Failed to add a line to the reading log: {:?}", error);
+                }
+            }
         }
 
-        // Update display if humidity value changed
-        if humidity != old_humidity_value {
-            old_humidity_value = humidity;
+        hour_statistics_row.set(&format_statistics(
+            statistics,
+            StatisticsWindow::Hour,
+            unit,
+            now_secs,
+        ));
+        day_statistics_row.set(&format_statistics(
+            statistics,
+            StatisticsWindow::Day,
+            unit,
+            now_secs,
+        ));
+    }
+}
 
-            let mut humidity_value_str = String::<8>::new();
-            let _ = write!(humidity_value_str, "{humidity}%");
+/// Format the derived metrics row, e.g. "DP 61.3F  HI 77.0F  HX 27  AH 10.2g/m3"
+fn format_derived_metrics(
+    psychrometrics: &Psychrometrics,
+    unit: TemperatureUnit,
+) -> String<SMALL_TEXT_ROW_CHARS> {
+    let mut row = String::<SMALL_TEXT_ROW_CHARS>::new();
+
+    for metric in DERIVED_METRICS {
+        let _ = match metric {
+            DerivedMetric::DewPoint => write!(
+                row,
+                "{} {:.1}{}  ",
+                metric.label(),
+                unit.convert_celsius(psychrometrics.dew_point),
+                unit.symbol()
+            ),
+            DerivedMetric::HeatIndex => write!(
+                row,
+                "{} {:.1}{}  ",
+                metric.label(),
+                unit.convert_celsius(psychrometrics.heat_index),
+                unit.symbol()
+            ),
+            DerivedMetric::Humidex => {
+                write!(row, "{} {:.0}  ", metric.label(), psychrometrics.humidex)
+            }
+            DerivedMetric::AbsoluteHumidity => write!(
+                row,
+                "{} {:.1}g/m3  ",
+                metric.label(),
+                psychrometrics.absolute_humidity
+            ),
+        };
+    }
 
-            // Count the number of characters in humidity value string to determine framebuffer width
-            let humidity_pixel_width =
-                (humidity_value_str.chars().count() * value_font_width) as u32;
+    row
+}
 
-            // Create humidity value
-            fb = Framebuffer::<Rgb565>::new(pixel_data, Size::new(humidity_pixel_width, 13));
-            Text::with_text_style(
-                &humidity_value_str,
-                Point::new(0, 12),
-                humidity_char_style,
-                text_style,
-            )
-            .draw(&mut fb)
-            .unwrap();
-            display.flush(&fb, Point::new(220, 100)).await;
-        }
+/// Format a line of the reading log, e.g. "01:02:03  T 72F  H 45%  P 1013hPa"
+fn format_log_line(
+    now_secs: u64,
+    temperature: i16,
+    humidity: Option<i8>,
+    pressure: Option<u16>,
+    unit: TemperatureUnit,
+) -> String<SMALL_TEXT_ROW_CHARS> {
+    let mut line = String::<SMALL_TEXT_ROW_CHARS>::new();
+    let _ = write!(
+        line,
+        "{:02}:{:02}:{:02}  T {}{}",
+        now_secs / 3600,
+        now_secs / 60 % 60,
+        now_secs % 60,
+        temperature,
+        unit.symbol()
+    );
+
+    if let Some(humidity) = humidity {
+        let _ = write!(line, "  H {humidity}%");
+    }
+
+    if let Some(pressure) = pressure {
+        let _ = write!(line, "  P {pressure}hPa");
     }
+
+    line
 }
 
-#[embassy_executor::task]
-async fn read_dht12_task(
-    mut i2c: I2c<'static, I2C0, Async>,
-    sender: Sender<'static, NoopRawMutex, Dht12Reading, 2>,
-) {
-    loop {
-        info!("DHT12 Read Loop");
-        let mut data = [0u8; 5];
-        i2c.write_read(0x5c, &[0x00], &mut data).await.unwrap();
-
-        /*
-        esp_println::println!(
-            "DHT12  B0:{:02x?}  B1:{:02x?}  B2:{:02x?}  B3:{:02x?}  B4:{:02x?}",
-            data[0],
-            data[1],
-            data[2],
-            data[3],
-            data[4]
+/// Format one row of the statistics region, e.g. "1H  T 68.2/75.1 ~71.3 sd1.2  H 40/55 ~47"
+fn format_statistics(
+    statistics: &EnvironmentStatistics,
+    window: StatisticsWindow,
+    unit: TemperatureUnit,
+    now_secs: u64,
+) -> String<SMALL_TEXT_ROW_CHARS> {
+    let mut row = String::<SMALL_TEXT_ROW_CHARS>::new();
+    let _ = write!(row, "{:<4}", window.label());
+
+    if let Some(t) = statistics.temperature(window, now_secs) {
+        let celsius = |tenths: i16| unit.convert_celsius(tenths as f32 / 10.0);
+        let _ = write!(
+            row,
+            "T {:.1}/{:.1} ~{:.1} sd{:.1}",
+            celsius(t.min),
+            celsius(t.max),
+            celsius(t.mean),
+            unit.convert_celsius_difference(t.std_dev as f32 / 10.0)
         );
-        */
+    }
 
-        let humidity: f32 = data[0] as f32 + (data[1] as f32) * 0.1;
-        let mut temp_celsius: f32 = (data[2] & 0x7F) as f32 + (data[3] as f32) * 0.1;
+    if let Some(h) = statistics.humidity(window, now_secs) {
+        let _ = write!(row, "  H {}/{} ~{}", h.min / 10, h.max / 10, h.mean / 10);
+    }
 
-        if (data[3] & 0x80) != 0 {
-            temp_celsius *= -1.0;
+    row
+}
+
+/// Check that a display answers and uses the configuration sent by the driver.
+///
+/// A failed check is only logged, the display still works without reading from it.
+async fn check_display(display: &mut Display) {
+    match display.is_attached().await {
+        Ok(true) => {}
+        Ok(false) => {
+            warn!("No display attached");
+            return;
+        }
+        Err(error) => {
+            warn!("Failed to read from the display: {:?}", error);
+            return;
         }
-        let temp_fahrenheit: f32 = ((temp_celsius * 9.0) / 5.0) + 32.0;
+    }
 
-        sender
-            .send(Dht12Reading {
-                humidity,
-                temp_fahrenheit,
-            })
-            .await;
+    match display.read_id().await {
+        Ok(id) => info!("Display ID = {:?}", id),
+        Err(error) => warn!("Failed to read the display ID: {:?}", error),
+    }
+
+    match display.verify_configuration().await {
+        Ok(true) => {}
+        Ok(false) => warn!("Display does not use the configuration sent by the driver"),
+        Err(error) => warn!("Failed to verify the display configuration: {:?}", error),
+    }
+}
+
+#[embassy_executor::task]
+async fn flush_task(display: &'static Mutex<NoopRawMutex, Display>, mut flusher: Flusher) {
+    loop {
+        // The renderer draws in the other half of the pixel data while this one is sent
+        let flush = flusher.receive().await;
+        if let Err(error) = display
+            .lock()
+            .await
+            .write_pixels(flush.data(), flush.area())
+            .await
+        {
+            // Drop the pixels, one bad area must not stop the display updates
+            error!(
+                "Failed to send {:?} to the display: {:?}",
+                flush.area(),
+                error
+            );
+        }
+        flusher.release(flush);
+    }
+}
+
+#[embassy_executor::task]
+async fn read_sensor_task(
+    mut sampler: Sampler<Sensor>,
+    sender: Sender<'static, NoopRawMutex, SensorStatus, 2>,
+) {
+    loop {
+        info!("{} Read Loop", sampler.sensor().name());
+
+        let status = sampler.sample(&mut Delay).await;
+        sender.send(status).await;
 
         Timer::after(SAMPLING_PERIOD).await;
     }
 }
 
+#[embassy_executor::task]
+async fn alarm_log_task(mut subscriber: AlarmSubscriber) {
+    loop {
+        match subscriber.next_message().await {
+            WaitResult::Message(AlarmEvent::Raised {
+                alarm,
+                value,
+                at_secs,
+            }) => warn!(
+                "ALARM RAISED {:?} {:?} = {} at {} s",
+                alarm.quantity, alarm.limit, value, at_secs
+            ),
+
+            WaitResult::Message(AlarmEvent::Cleared {
+                alarm,
+                value,
+                at_secs,
+            }) => info!(
+                "ALARM CLEARED {:?} {:?} = {} at {} s",
+                alarm.quantity, alarm.limit, value, at_secs
+            ),
+
+            WaitResult::Lagged(missed) => warn!("Missed {} alarm events", missed),
+        }
+    }
+}
+
 #[esp_hal_embassy::main]
 async fn main(spawner: Spawner) {
     esp_println::logger::init_logger_from_env();
@@ -376,9 +729,10 @@ async fn main(spawner: Spawner) {
     let bcklt = Output::new(io.pins.gpio32, Level::Low);
 
     info!("Create SPI bus");
-    let spi_bus = Spi::new(peripherals.SPI2, 10_000_u32.kHz(), SpiMode::Mode0)
+    let spi_bus = Spi::new(peripherals.SPI2, DISPLAY_SPI_KHZ.kHz(), SpiMode::Mode0)
         .with_sck(io.pins.gpio18)
-        .with_mosi(io.pins.gpio23);
+        .with_mosi(io.pins.gpio23)
+        .with_miso(io.pins.gpio19);
 
     info!("Wrap SPI bus in a SPI DMA");
     let dma = Dma::new(peripherals.DMA);
@@ -395,24 +749,75 @@ async fn main(spawner: Spawner) {
     let spi_device = ExclusiveDevice::new(spi_dma, cs, Delay).unwrap();
 
     info!("Create display");
-    let mut display = Ili9341::new(spi_device, dc, rst, bcklt, Config::default());
+    let config = Config::builder()
+        .panel_size(320, 240)
+        .rotation(Rotation::Deg0)
+        .inverted_color(ModeState::On)
+        .init_sequence(InitSequence::Vendor)
+        .chunk_size(4096)
+        .build()
+        .unwrap();
+    let mut display = Ili9341::new(spi_device, dc, rst, PinBacklight::new(bcklt), config);
 
     let start = Instant::now();
-    display.initialize(&mut Delay).await.unwrap();
+    if let Err(error) = display.initialize(&mut Delay).await {
+        error!("Failed to initialize the display: {:?}", error);
+    }
 
     warn!(
         "Display intialization took  {} milliseconds",
         Instant::now().duration_since(start).as_millis()
     );
 
+    // The display reads at most at about 6.6MHz, slow the SPI clock down for the check
+    display
+        .spi_device_mut()
+        .bus_mut()
+        .change_bus_frequency(DISPLAY_READ_SPI_KHZ.kHz());
+    check_display(&mut display).await;
+    display
+        .spi_device_mut()
+        .bus_mut()
+        .change_bus_frequency(DISPLAY_SPI_KHZ.kHz());
+
+    info!("Detect sensor");
+    let sensor = AnySensor::detect(i2c0, Delay).await;
+    let sensor_name = sensor.name();
+    let sampler = Sampler::new(sensor, RETRY_CONFIG);
+
+    // Share the display between the render and flush tasks
+    let display: &'static _ = DISPLAY.init(Mutex::new(display));
+
+    // Split the pixel data in two buffers handed back and forth between the render and flush tasks
+    let (first, second) = PIXEL_DATA.take().split_at_mut(FRAME_BUFFER_SIZE / 2);
+    let (double_buffer, flusher) = FLUSH_CHANNELS
+        .init(FlushChannels::new())
+        .split(first, second);
+
     // Create channel to communicate between both tasks
     let channel: &'static mut _ = CHANNEL.init(Channel::new());
     let receiver = channel.receiver();
     let sender = channel.sender();
 
+    // Create channel to publish alarm events to the other tasks
+    let alarm_channel: &'static mut _ = ALARM_CHANNEL.init(PubSubChannel::new());
+    let alarm_publisher = alarm_channel.publisher().unwrap();
+    let alarm_subscriber = alarm_channel.subscriber().unwrap();
+
     // Spawn our tasks
-    spawner.spawn(render_task(display, receiver)).ok();
-    spawner.spawn(read_dht12_task(i2c0, sender)).ok();
+    spawner
+        .spawn(render_task(
+            display,
+            double_buffer,
+            receiver,
+            alarm_publisher,
+            sensor_name,
+            TEMPERATURE_UNIT,
+        ))
+        .ok();
+    spawner.spawn(flush_task(display, flusher)).ok();
+    spawner.spawn(read_sensor_task(sampler, sender)).ok();
+    spawner.spawn(alarm_log_task(alarm_subscriber)).ok();
 
     loop {
         //warn!("Main Loop");
@@ -0,0 +1,62 @@
+use embedded_graphics_core::pixelcolor::{
+    raw::ToBytes, PixelColor, Rgb565, Rgb666, Rgb888, RgbColor,
+};
+
+/// Largest number of bytes per pixel of an [`InterfaceColor`]
+pub const MAX_BYTES_PER_PIXEL: usize = 3;
+
+/// Color format of the pixels sent to the display.
+///
+/// The ILI9341 takes 16 bits per pixel as `Rgb565` in 2 bytes, or 18 bits per
+/// pixel as 3 bytes with the 6 bits of every color in the upper bits of a byte.
+/// The 18 bit format shows smooth gradients without banding, but sends half as
+/// many bytes more per pixel.
+///
+/// # Implementations
+///
+/// - `Rgb565`: 16 bits per pixel, 2 bytes.
+/// - `Rgb666`: 18 bits per pixel, 3 bytes.
+/// - `Rgb888`: 18 bits per pixel, 3 bytes, the display ignores the 2 lowest bits of every color.
+pub trait InterfaceColor: PixelColor + From<Rgb666> {
+    /// Bytes of a pixel.
+    type Bytes: AsRef<[u8]>;
+
+    /// Parameter of the pixel format set command for this color format.
+    const PIXEL_FORMAT: u8;
+
+    /// Number of bytes sent per pixel.
+    const BYTES_PER_PIXEL: usize = core::mem::size_of::<Self::Bytes>();
+
+    /// Bytes of the pixel in the order they are sent to the display
+    fn to_interface_bytes(self) -> Self::Bytes;
+}
+
+impl InterfaceColor for Rgb565 {
+    type Bytes = [u8; 2];
+
+    const PIXEL_FORMAT: u8 = 0x55;
+
+    fn to_interface_bytes(self) -> Self::Bytes {
+        self.to_be_bytes()
+    }
+}
+
+impl InterfaceColor for Rgb666 {
+    type Bytes = [u8; 3];
+
+    const PIXEL_FORMAT: u8 = 0x66;
+
+    fn to_interface_bytes(self) -> Self::Bytes {
+        [self.r() << 2, self.g() << 2, self.b() << 2]
+    }
+}
+
+impl InterfaceColor for Rgb888 {
+    type Bytes = [u8; 3];
+
+    const PIXEL_FORMAT: u8 = 0x66;
+
+    fn to_interface_bytes(self) -> Self::Bytes {
+        self.to_be_bytes()
+    }
+}
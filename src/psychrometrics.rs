@@ -0,0 +1,201 @@
+use libm::{expf, fabsf, logf, sqrtf};
+
+use crate::sensor::EnvironmentReading;
+
+/// Magnus formula coefficient b over water (Sonntag 1990)
+const MAGNUS_B: f32 = 17.62;
+
+/// Magnus formula coefficient c in degrees celsius over water (Sonntag 1990)
+const MAGNUS_C: f32 = 243.12;
+
+/// Saturation vapour pressure at 0 degrees celsius in hectopascal
+const MAGNUS_A: f32 = 6.112;
+
+/// Specific gas constant of water vapour in J/(kg K)
+const WATER_VAPOUR_GAS_CONSTANT: f32 = 461.5;
+
+/// Values derived from a temperature and relative humidity reading that can be shown
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DerivedMetric {
+    DewPoint,
+    HeatIndex,
+    Humidex,
+    AbsoluteHumidity,
+}
+
+impl DerivedMetric {
+    /// Short label shown in front of the value on the display
+    pub fn label(&self) -> &'static str {
+        match self {
+            DerivedMetric::DewPoint => "DP",
+            DerivedMetric::HeatIndex => "HI",
+            DerivedMetric::Humidex => "HX",
+            DerivedMetric::AbsoluteHumidity => "AH",
+        }
+    }
+}
+
+/// Metrics derived from the air temperature and relative humidity
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Psychrometrics {
+    /// Dew point in degrees celsius
+    pub dew_point: f32,
+    /// Heat index (apparent temperature) in degrees celsius
+    pub heat_index: f32,
+    /// Humidex, a dimensionless value on the celsius scale
+    pub humidex: f32,
+    /// Absolute humidity in grams of water per cubic meter
+    pub absolute_humidity: f32,
+}
+
+impl Psychrometrics {
+    /// Compute all metrics for a temperature in degrees celsius and a relative
+    /// humidity in percent.
+    pub fn new(celsius: f32, humidity: f32) -> Self {
+        Self {
+            dew_point: dew_point(celsius, humidity),
+            heat_index: heat_index(celsius, humidity),
+            humidex: humidex(celsius, humidity),
+            absolute_humidity: absolute_humidity(celsius, humidity),
+        }
+    }
+
+    /// Compute all metrics for a reading, `None` if the sensor does not measure humidity
+    pub fn from_reading(reading: &EnvironmentReading) -> Option<Self> {
+        reading
+            .humidity()
+            .map(|humidity| Self::new(reading.celsius(), humidity))
+    }
+}
+
+/// Saturation vapour pressure over water in hectopascal (Magnus formula)
+pub fn saturation_vapour_pressure(celsius: f32) -> f32 {
+    MAGNUS_A * expf(MAGNUS_B * celsius / (MAGNUS_C + celsius))
+}
+
+/// Dew point in degrees celsius using the Magnus formula
+///
+/// A humidity of 0% has no dew point, it is clamped to 0.1% to keep the result finite.
+pub fn dew_point(celsius: f32, humidity: f32) -> f32 {
+    let gamma = logf(humidity.max(0.1) / 100.0) + MAGNUS_B * celsius / (MAGNUS_C + celsius);
+    MAGNUS_C * gamma / (MAGNUS_B - gamma)
+}
+
+/// Heat index in degrees celsius using the US National Weather Service algorithm
+///
+/// Below about 27 degrees celsius the simple Steadman formula is used, above it the
+/// Rothfusz regression with the adjustments for low and high humidity.
+pub fn heat_index(celsius: f32, humidity: f32) -> f32 {
+    let t = celsius * 9.0 / 5.0 + 32.0;
+    let rh = humidity;
+
+    let simple = 0.5 * (t + 61.0 + ((t - 68.0) * 1.2) + (rh * 0.094));
+    let fahrenheit = if (simple + t) / 2.0 < 80.0 {
+        simple
+    } else {
+        let mut hi = -42.379 + 2.049_015_2 * t + 10.143_331 * rh
+            - 0.224_755_42 * t * rh
+            - 0.006_837_83 * t * t
+            - 0.054_817_17 * rh * rh
+            + 0.001_228_74 * t * t * rh
+            + 0.000_852_82 * t * rh * rh
+            - 0.000_001_99 * t * t * rh * rh;
+
+        if rh < 13.0 && (80.0..=112.0).contains(&t) {
+            hi -= ((13.0 - rh) / 4.0) * sqrtf((17.0 - fabsf(t - 95.0)) / 17.0);
+        } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+            hi += ((rh - 85.0) / 10.0) * ((87.0 - t) / 5.0);
+        }
+
+        hi
+    };
+
+    (fahrenheit - 32.0) * 5.0 / 9.0
+}
+
+/// Humidex as defined by Environment Canada
+pub fn humidex(celsius: f32, humidity: f32) -> f32 {
+    let dew_point_kelvin = dew_point(celsius, humidity) + 273.15;
+    let vapour_pressure = 6.11 * expf(5417.753 * (1.0 / 273.16 - 1.0 / dew_point_kelvin));
+    celsius + 0.5555 * (vapour_pressure - 10.0)
+}
+
+/// Absolute humidity in grams of water per cubic meter
+pub fn absolute_humidity(celsius: f32, humidity: f32) -> f32 {
+    // Vapour pressure in pascal divided by R * T gives kg/m3
+    let vapour_pressure = saturation_vapour_pressure(celsius) * 100.0 * humidity / 100.0;
+    vapour_pressure / (WATER_VAPOUR_GAS_CONSTANT * (celsius + 273.15)) * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fahrenheit_to_celsius(fahrenheit: f32) -> f32 {
+        (fahrenheit - 32.0) * 5.0 / 9.0
+    }
+
+    fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{actual} is not within {tolerance} of {expected}"
+        );
+    }
+
+    #[test]
+    fn heat_index_matches_the_nws_table() {
+        // Air temperature and relative humidity, heat index in fahrenheit from the
+        // NWS heat index chart, which is rounded to whole degrees
+        for (fahrenheit, humidity, expected) in [
+            (80.0, 40.0, 80.0),
+            (90.0, 70.0, 106.0),
+            (100.0, 50.0, 118.0),
+            (104.0, 40.0, 119.0),
+        ] {
+            let celsius = fahrenheit_to_celsius(fahrenheit);
+            assert_close(
+                heat_index(celsius, humidity),
+                fahrenheit_to_celsius(expected),
+                1.0,
+            );
+        }
+
+        assert_close(heat_index(32.0, 70.0), 41.0, 1.0);
+    }
+
+    #[test]
+    fn humidex_matches_environment_canada() {
+        // 30 degrees celsius with a dew point of 15 degrees is a humidex of 34
+        assert_close(humidex(30.0, 40.2), 34.0, 0.5);
+        // Humidex table: 30 degrees celsius at 70 percent is 41
+        assert_close(humidex(30.0, 70.0), 41.0, 0.5);
+    }
+
+    #[test]
+    fn magnus_dew_point() {
+        assert_close(dew_point(25.0, 60.0), 16.7, 0.1);
+        assert_close(dew_point(20.0, 50.0), 9.3, 0.1);
+        assert_close(dew_point(0.0, 100.0), 0.0, 0.01);
+        assert_close(dew_point(-10.0, 80.0), -12.8, 0.1);
+    }
+
+    #[test]
+    fn absolute_humidity_of_saturated_air() {
+        // Saturated water vapour density, 4.85 g/m3 at 0, 17.3 at 20 and 30.4 at 30 degrees celsius
+        assert_close(absolute_humidity(0.0, 100.0), 4.85, 0.05);
+        assert_close(absolute_humidity(20.0, 100.0), 17.3, 0.1);
+        assert_close(absolute_humidity(30.0, 100.0), 30.4, 0.2);
+        assert_close(absolute_humidity(25.0, 50.0), 11.5, 0.1);
+    }
+
+    #[test]
+    fn no_metrics_without_humidity() {
+        let reading = EnvironmentReading {
+            temp_tenths_celsius: 215,
+            humidity_tenths: None,
+            pressure_pa: Some(101_325),
+        };
+
+        assert_eq!(Psychrometrics::from_reading(&reading), None);
+    }
+}
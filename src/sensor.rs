@@ -0,0 +1,615 @@
+use log::*;
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+use crate::aht20::Aht20;
+use crate::bmp280::Bmp280;
+use crate::dht12::{Dht12, Dht12Error};
+use crate::sht3x::Sht3x;
+
+/// Unit used to present a temperature
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+}
+
+impl TemperatureUnit {
+    /// Symbol shown after a temperature value on the display
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "C",
+            TemperatureUnit::Fahrenheit => "F",
+            TemperatureUnit::Kelvin => "K",
+        }
+    }
+
+    /// Convert a temperature in degrees celsius to this unit
+    pub fn convert_celsius(&self, celsius: f32) -> f32 {
+        match self {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => ((celsius * 9.0) / 5.0) + 32.0,
+            TemperatureUnit::Kelvin => celsius + 273.15,
+        }
+    }
+
+    /// Convert a temperature difference in degrees celsius to this unit
+    pub fn convert_celsius_difference(&self, celsius: f32) -> f32 {
+        match self {
+            TemperatureUnit::Celsius | TemperatureUnit::Kelvin => celsius,
+            TemperatureUnit::Fahrenheit => (celsius * 9.0) / 5.0,
+        }
+    }
+}
+
+/// A single reading from an environment sensor
+///
+/// Temperature and humidity are stored as fixed point integers in tenths. Sensors
+/// that do not measure humidity or pressure leave those values as `None`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnvironmentReading {
+    /// Temperature in tenths of a degree celsius
+    pub temp_tenths_celsius: i16,
+    /// Relative humidity in tenths of a percent
+    pub humidity_tenths: Option<u16>,
+    /// Barometric pressure in pascal
+    pub pressure_pa: Option<u32>,
+}
+
+impl EnvironmentReading {
+    /// Temperature in degrees celsius
+    pub fn celsius(&self) -> f32 {
+        self.temp_tenths_celsius as f32 / 10.0
+    }
+
+    /// Temperature in degrees fahrenheit
+    pub fn fahrenheit(&self) -> f32 {
+        TemperatureUnit::Fahrenheit.convert_celsius(self.celsius())
+    }
+
+    /// Temperature in kelvin
+    pub fn kelvin(&self) -> f32 {
+        TemperatureUnit::Kelvin.convert_celsius(self.celsius())
+    }
+
+    /// Temperature in the requested unit
+    pub fn temperature(&self, unit: TemperatureUnit) -> f32 {
+        unit.convert_celsius(self.celsius())
+    }
+
+    /// Relative humidity in percent
+    pub fn humidity(&self) -> Option<f32> {
+        self.humidity_tenths.map(|h| h as f32 / 10.0)
+    }
+
+    /// Barometric pressure in hectopascal
+    pub fn pressure_hpa(&self) -> Option<f32> {
+        self.pressure_pa.map(|p| p as f32 / 100.0)
+    }
+}
+
+/// Message sent from the sampling task to the tasks that consume the readings
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SensorStatus {
+    /// A valid reading from the sensor
+    Reading(EnvironmentReading),
+
+    /// Reading the sensor failed even after retrying
+    Fault {
+        error: SensorError,
+        consecutive_failures: u32,
+    },
+}
+
+/// Retry behaviour used when reading the sensor fails
+///
+/// The delay between retries starts at `initial_backoff_ms` and doubles after every
+/// failed attempt until it reaches `max_backoff_ms`.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryConfig {
+    /// Number of retries after the first failed attempt
+    pub max_retries: u8,
+    /// Delay before the first retry in milliseconds
+    pub initial_backoff_ms: u32,
+    /// Upper limit of the delay between retries in milliseconds
+    pub max_backoff_ms: u32,
+}
+
+/// Async temperature, humidity and pressure sensor.
+///
+/// Every sensor reports a temperature, humidity and pressure are reported by the
+/// sensors that support them.
+#[allow(async_fn_in_trait)]
+pub trait EnvironmentSensor {
+    /// Name of the sensor, shown in the screen title
+    fn name(&self) -> &'static str;
+
+    /// Prepare the sensor for measurements, e.g. reset it or load its calibration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sensor does not respond or is not the expected chip.
+    async fn init(&mut self) -> Result<(), SensorError> {
+        Ok(())
+    }
+
+    /// Measure the temperature and, if supported, humidity and pressure.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the I2C transfer fails or the data is not valid.
+    async fn read(&mut self) -> Result<EnvironmentReading, SensorError>;
+}
+
+/// Samples an environment sensor, retrying failed reads and counting the failures.
+///
+/// The sensor is initialized before the first read and again after a read failed,
+/// so a sensor that was reset or reconnected recovers by itself.
+pub struct Sampler<S>
+where
+    S: EnvironmentSensor,
+{
+    /// Sensor being sampled.
+    sensor: S,
+    /// Retry behaviour when a read fails.
+    retry: RetryConfig,
+    /// Whether the sensor has been initialized since the last failure.
+    initialized: bool,
+    /// Number of samples in a row that failed after all retries.
+    consecutive_failures: u32,
+}
+
+impl<S> Sampler<S>
+where
+    S: EnvironmentSensor,
+{
+    /// Creates a new sampler for the sensor.
+    pub fn new(sensor: S, retry: RetryConfig) -> Self {
+        Self {
+            sensor,
+            retry,
+            initialized: false,
+            consecutive_failures: 0,
+        }
+    }
+
+    /// Sensor being sampled
+    pub fn sensor(&self) -> &S {
+        &self.sensor
+    }
+
+    /// Number of samples in a row that failed after all retries, reset on a good sample
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    /// Read the sensor, retrying with an exponential backoff when the read fails.
+    ///
+    /// # Parameters
+    ///
+    /// - `delay`: A mutable reference to an implementation of the `DelayNs` trait.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the last attempt if all attempts fail.
+    pub async fn read_with_retry<D>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<EnvironmentReading, SensorError>
+    where
+        D: DelayNs,
+    {
+        let mut backoff_ms = self.retry.initial_backoff_ms;
+        let mut attempt = 0;
+
+        loop {
+            match self.read_once().await {
+                Ok(reading) => {
+                    self.consecutive_failures = 0;
+                    return Ok(reading);
+                }
+
+                Err(e) if attempt < self.retry.max_retries => {
+                    warn!(
+                        "{} read failed: {:?}, retry in {} ms",
+                        self.sensor.name(),
+                        e,
+                        backoff_ms
+                    );
+                    delay.delay_ms(backoff_ms).await;
+                    backoff_ms = backoff_ms.saturating_mul(2).min(self.retry.max_backoff_ms);
+                    attempt += 1;
+                }
+
+                Err(e) => {
+                    self.initialized = false;
+                    self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    /// Read the sensor and turn the result into a message for the consuming tasks
+    pub async fn sample<D>(&mut self, delay: &mut D) -> SensorStatus
+    where
+        D: DelayNs,
+    {
+        match self.read_with_retry(delay).await {
+            Ok(reading) => SensorStatus::Reading(reading),
+
+            Err(error) => {
+                error!("{} read failed: {:?}", self.sensor.name(), error);
+                SensorStatus::Fault {
+                    error,
+                    consecutive_failures: self.consecutive_failures,
+                }
+            }
+        }
+    }
+
+    /// Initialize the sensor if needed and read it once
+    async fn read_once(&mut self) -> Result<EnvironmentReading, SensorError> {
+        if !self.initialized {
+            self.sensor.init().await?;
+            self.initialized = true;
+        }
+
+        self.sensor.read().await
+    }
+}
+
+/// Any of the sensors supported by this firmware.
+///
+/// Allows the same firmware to run on boards with different sensor bases, the
+/// sensor that is attached is found with [`AnySensor::detect`].
+pub enum AnySensor<I2C, D>
+where
+    I2C: I2c,
+    D: DelayNs,
+{
+    Sht3x(Sht3x<I2C, D>),
+    Aht20(Aht20<I2C, D>),
+    Bmp280(Bmp280<I2C, D>),
+    Dht12(Dht12<I2C>),
+}
+
+impl<I2C, D> AnySensor<I2C, D>
+where
+    I2C: I2c,
+    D: DelayNs + Clone,
+{
+    /// Probe the I2C bus for a supported sensor.
+    ///
+    /// The sensors are tried one after the other by initializing them, the DHT12
+    /// has no initialization so it is tried with a read. The humidity sensors are
+    /// tried before the BMP280, so the M5Stack ENV unit with a DHT12 and a BMP280
+    /// keeps its humidity. The DHT12 is also used when no sensor answers, a missing
+    /// sensor then shows up as a sensor fault.
+    pub async fn detect(i2c: I2C, delay: D) -> Self {
+        let mut sht3x = Sht3x::new(i2c, delay.clone());
+        if sht3x.init().await.is_ok() {
+            info!("Detected SHT3x");
+            return AnySensor::Sht3x(sht3x);
+        }
+
+        let mut aht20 = Aht20::new(sht3x.release(), delay.clone());
+        if aht20.init().await.is_ok() {
+            info!("Detected AHT20");
+            return AnySensor::Aht20(aht20);
+        }
+
+        // A DHT12 that answers with a bad frame is still there
+        let mut dht12 = Dht12::new(aht20.release());
+        if !matches!(dht12.read().await, Err(Dht12Error::I2c(_))) {
+            info!("Detected DHT12");
+            return AnySensor::Dht12(dht12);
+        }
+
+        let mut bmp280 = Bmp280::new(dht12.release(), delay);
+        if bmp280.init().await.is_ok() {
+            info!("Detected {}", bmp280.name());
+            return AnySensor::Bmp280(bmp280);
+        }
+
+        info!("No sensor detected, using DHT12");
+        AnySensor::Dht12(Dht12::new(bmp280.release()))
+    }
+}
+
+impl<I2C, D> EnvironmentSensor for AnySensor<I2C, D>
+where
+    I2C: I2c,
+    D: DelayNs,
+{
+    fn name(&self) -> &'static str {
+        match self {
+            AnySensor::Sht3x(sensor) => sensor.name(),
+            AnySensor::Aht20(sensor) => sensor.name(),
+            AnySensor::Bmp280(sensor) => sensor.name(),
+            AnySensor::Dht12(sensor) => EnvironmentSensor::name(sensor),
+        }
+    }
+
+    async fn init(&mut self) -> Result<(), SensorError> {
+        match self {
+            AnySensor::Sht3x(sensor) => sensor.init().await,
+            AnySensor::Aht20(sensor) => sensor.init().await,
+            AnySensor::Bmp280(sensor) => sensor.init().await,
+            AnySensor::Dht12(sensor) => EnvironmentSensor::init(sensor).await,
+        }
+    }
+
+    async fn read(&mut self) -> Result<EnvironmentReading, SensorError> {
+        match self {
+            AnySensor::Sht3x(sensor) => sensor.read().await,
+            AnySensor::Aht20(sensor) => sensor.read().await,
+            AnySensor::Bmp280(sensor) => sensor.read().await,
+            AnySensor::Dht12(sensor) => EnvironmentSensor::read(sensor).await,
+        }
+    }
+}
+
+/// CRC-8 used by the Sensirion and Aosong sensors, polynomial 0x31 with initial value 0xff
+pub(crate) fn crc8(data: &[u8]) -> u8 {
+    let mut crc: u8 = 0xff;
+    for byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x31
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/******************************************************************************************************
+*                                  IMPLEMENT CUSTOM ERRORS
+*****************************************************************************************************/
+
+use embedded_hal::i2c::Error as I2cError;
+use embedded_hal::i2c::ErrorKind as I2cErrorKind;
+
+/// Error Types shared by the sensor drivers
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SensorError {
+    /// An error in the underlying I2C bus
+    I2c(I2cErrorKind),
+
+    /// The checksum sent by the sensor does not match the data
+    Checksum,
+
+    /// The sensor returned a value outside of its measurement range
+    OutOfRange,
+
+    /// The sensor has not finished the measurement
+    Busy,
+
+    /// The chip answering on the sensor address is not the expected one
+    UnknownChip(u8),
+}
+
+impl<E> From<E> for SensorError
+where
+    E: I2cError,
+{
+    fn from(error: E) -> Self {
+        Self::I2c(error.kind())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+    use std::vec::Vec;
+
+    use embedded_hal::i2c::NoAcknowledgeSource;
+
+    use super::*;
+    use crate::aht20::AHT20_ADDRESS;
+    use crate::bmp280::BMP280_ADDRESS;
+    use crate::dht12::DHT12_ADDRESS;
+    use crate::sht3x::SHT3X_ADDRESS;
+    use crate::test_util::{block_on, Event, I2cResponse, MockI2c, Recorder};
+
+    const NACK: SensorError =
+        SensorError::I2c(I2cErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+
+    const RETRY: RetryConfig = RetryConfig {
+        max_retries: 3,
+        initial_backoff_ms: 50,
+        max_backoff_ms: 150,
+    };
+
+    /// DHT12 frame of 21.5 degrees celsius and 45.3 percent
+    fn dht12_frame() -> I2cResponse {
+        I2cResponse::Read(vec![45, 3, 21, 5, 74])
+    }
+
+    /// Responses of a bus failing `failures` times before answering with a frame
+    fn failing(failures: usize) -> impl Iterator<Item = I2cResponse> {
+        (0..failures)
+            .map(|_| I2cResponse::Nack)
+            .chain([dht12_frame()])
+    }
+
+    fn delays(recorder: &Recorder) -> Vec<u32> {
+        recorder
+            .take()
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::Delay { ms } => Some(ms),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn retry_backoff_doubles_up_to_the_maximum() {
+        let recorder = Recorder::default();
+        let mut delay = recorder.delay();
+        let config = RetryConfig {
+            max_retries: 4,
+            ..RETRY
+        };
+        let mut sampler = Sampler::new(Dht12::new(MockI2c::new([])), config);
+
+        assert_eq!(block_on(sampler.read_with_retry(&mut delay)), Err(NACK));
+        assert_eq!(delays(&recorder), [50, 100, 150, 150]);
+        assert_eq!(sampler.consecutive_failures(), 1);
+    }
+
+    #[test]
+    fn retry_succeeds_within_the_retries() {
+        let recorder = Recorder::default();
+        let mut delay = recorder.delay();
+        let mut sampler = Sampler::new(Dht12::new(MockI2c::new(failing(3))), RETRY);
+
+        let reading = block_on(sampler.read_with_retry(&mut delay)).unwrap();
+
+        assert_eq!(reading.temp_tenths_celsius, 215);
+        assert_eq!(delays(&recorder), [50, 100, 150]);
+        assert_eq!(sampler.consecutive_failures(), 0);
+    }
+
+    #[test]
+    fn sample_reports_a_fault_when_the_retries_are_used_up() {
+        let recorder = Recorder::default();
+        let mut delay = recorder.delay();
+        // Every sample tries the first read and the 3 retries
+        let mut sampler = Sampler::new(Dht12::new(MockI2c::new(failing(2 * 4))), RETRY);
+
+        for consecutive_failures in 1..=2 {
+            assert_eq!(
+                block_on(sampler.sample(&mut delay)),
+                SensorStatus::Fault {
+                    error: NACK,
+                    consecutive_failures
+                }
+            );
+        }
+
+        assert!(matches!(
+            block_on(sampler.sample(&mut delay)),
+            SensorStatus::Reading(_)
+        ));
+        assert_eq!(sampler.consecutive_failures(), 0);
+    }
+
+    #[test]
+    fn sampler_initializes_the_sensor_again_after_a_fault() {
+        const RESET: [u8; 2] = [0x30, 0xa2];
+        const MEASURE: [u8; 2] = [0x24, 0x00];
+
+        // 25.0 degrees celsius and 40.0 percent
+        let (temperature, humidity) = (0x6666_u16.to_be_bytes(), 0x6666_u16.to_be_bytes());
+        let data = [
+            &temperature[..],
+            &[crc8(&temperature)],
+            &humidity[..],
+            &[crc8(&humidity)],
+        ]
+        .concat();
+        let ok = || I2cResponse::Read(vec![]);
+
+        let responses = [
+            // Reset, measure and read
+            ok(),
+            ok(),
+            I2cResponse::Read(data.clone()),
+            // The measurement is not acknowledged
+            I2cResponse::Nack,
+            // Reset again, measure and read
+            ok(),
+            ok(),
+            I2cResponse::Read(data),
+        ];
+        let sensor = Sht3x::new(MockI2c::new(responses), Recorder::default().delay());
+        let config = RetryConfig {
+            max_retries: 0,
+            ..RETRY
+        };
+        let mut sampler = Sampler::new(sensor, config);
+        let mut delay = Recorder::default().delay();
+
+        let expected = SensorStatus::Reading(EnvironmentReading {
+            temp_tenths_celsius: 250,
+            humidity_tenths: Some(400),
+            pressure_pa: None,
+        });
+        assert_eq!(block_on(sampler.sample(&mut delay)), expected);
+        assert!(matches!(
+            block_on(sampler.sample(&mut delay)),
+            SensorStatus::Fault { .. }
+        ));
+        assert_eq!(block_on(sampler.sample(&mut delay)), expected);
+
+        let Sampler { sensor, .. } = sampler;
+        assert_eq!(
+            sensor.release().writes,
+            [
+                RESET.to_vec(),
+                MEASURE.to_vec(),
+                vec![],
+                MEASURE.to_vec(),
+                RESET.to_vec(),
+                MEASURE.to_vec(),
+                vec![],
+            ]
+        );
+    }
+
+    #[test]
+    fn detect_prefers_the_dht12_to_the_bmp280() {
+        // The ENV unit has a DHT12 and a BMP280, the SHT3x and AHT20 do not answer
+        let mut i2c = MockI2c::new([I2cResponse::Nack, I2cResponse::Nack, dht12_frame()]);
+
+        let sensor = block_on(AnySensor::detect(&mut i2c, Recorder::default().delay()));
+
+        assert_eq!(sensor.name(), "DHT12");
+        assert_eq!(i2c.addresses, [SHT3X_ADDRESS, AHT20_ADDRESS, DHT12_ADDRESS]);
+    }
+
+    #[test]
+    fn detect_falls_back_to_the_bmp280_after_the_humidity_sensors() {
+        let mut i2c = MockI2c::new([
+            I2cResponse::Nack,
+            I2cResponse::Nack,
+            I2cResponse::Nack,
+            I2cResponse::Read(vec![0x58]),
+            I2cResponse::Read(vec![0; 24]),
+        ]);
+
+        let sensor = block_on(AnySensor::detect(&mut i2c, Recorder::default().delay()));
+
+        assert_eq!(sensor.name(), "BMP280");
+        assert_eq!(
+            i2c.addresses,
+            [
+                SHT3X_ADDRESS,
+                AHT20_ADDRESS,
+                DHT12_ADDRESS,
+                BMP280_ADDRESS,
+                BMP280_ADDRESS
+            ]
+        );
+    }
+
+    #[test]
+    fn detect_uses_the_dht12_when_no_sensor_answers() {
+        let mut i2c = MockI2c::new([]);
+
+        let sensor = block_on(AnySensor::detect(&mut i2c, Recorder::default().delay()));
+
+        assert_eq!(sensor.name(), "DHT12");
+        assert_eq!(
+            i2c.addresses,
+            [SHT3X_ADDRESS, AHT20_ADDRESS, DHT12_ADDRESS, BMP280_ADDRESS]
+        );
+    }
+}
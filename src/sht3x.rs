@@ -0,0 +1,162 @@
+use log::*;
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+use crate::sensor::{crc8, EnvironmentReading, EnvironmentSensor, SensorError};
+
+/// I2C address of the SHT3x sensor with the ADDR pin low
+pub const SHT3X_ADDRESS: u8 = 0x44;
+
+/// Command for Soft Reset
+const SOFT_RESET: [u8; 2] = [0x30, 0xa2];
+
+/// Command for Single Shot Measurement, high repeatability, clock stretching disabled
+const MEASURE_HIGH_REPEATABILITY: [u8; 2] = [0x24, 0x00];
+
+/// Time the sensor needs to come back after a soft reset in milliseconds
+const SOFT_RESET_TIME_MS: u32 = 2;
+
+/// Time a high repeatability measurement takes in milliseconds
+const MEASUREMENT_TIME_MS: u32 = 16;
+
+/// SHT3x (SHT30, SHT31, SHT35) async temperature and humidity sensor driver.
+///
+/// # Type Parameters
+///
+/// - `I2C`: The I2C bus the sensor is connected to.
+/// - `D`: The delay used to wait for the measurement to finish.
+///
+/// # Constraints
+///
+/// - `I2C`: Must implement the async `I2c` trait.
+/// - `D`: Must implement the async `DelayNs` trait.
+pub struct Sht3x<I2C, D>
+where
+    I2C: I2c,
+    D: DelayNs,
+{
+    /// I2C bus used for communication with the sensor.
+    i2c: I2C,
+    /// Delay used to wait for the measurement to finish.
+    delay: D,
+    /// I2C address of the sensor.
+    address: u8,
+}
+
+impl<I2C, D> Sht3x<I2C, D>
+where
+    I2C: I2c,
+    D: DelayNs,
+{
+    /// Creates a new driver instance using the default SHT3x address.
+    pub fn new(i2c: I2C, delay: D) -> Self {
+        Self {
+            i2c,
+            delay,
+            address: SHT3X_ADDRESS,
+        }
+    }
+
+    /// Release the I2C bus used by the driver
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+}
+
+impl<I2C, D> EnvironmentSensor for Sht3x<I2C, D>
+where
+    I2C: I2c,
+    D: DelayNs,
+{
+    fn name(&self) -> &'static str {
+        "SHT3X"
+    }
+
+    async fn init(&mut self) -> Result<(), SensorError> {
+        debug!("SHT3x soft reset");
+        self.i2c.write(self.address, &SOFT_RESET).await?;
+        self.delay.delay_ms(SOFT_RESET_TIME_MS).await;
+
+        Ok(())
+    }
+
+    async fn read(&mut self) -> Result<EnvironmentReading, SensorError> {
+        self.i2c
+            .write(self.address, &MEASURE_HIGH_REPEATABILITY)
+            .await?;
+        self.delay.delay_ms(MEASUREMENT_TIME_MS).await;
+
+        // Temperature word, CRC, humidity word, CRC
+        let mut data = [0u8; 6];
+        self.i2c.read(self.address, &mut data).await?;
+
+        if crc8(&data[0..2]) != data[2] || crc8(&data[3..5]) != data[5] {
+            return Err(SensorError::Checksum);
+        }
+
+        let raw_temperature = u16::from_be_bytes([data[0], data[1]]) as i32;
+        let raw_humidity = u16::from_be_bytes([data[3], data[4]]) as u32;
+
+        // T = -45 + 175 * raw / (2^16 - 1) and RH = 100 * raw / (2^16 - 1), both in tenths
+        let temp_tenths_celsius = (-450 + (1750 * raw_temperature) / 65535) as i16;
+        let humidity_tenths = ((1000 * raw_humidity) / 65535) as u16;
+
+        Ok(EnvironmentReading {
+            temp_tenths_celsius,
+            humidity_tenths: Some(humidity_tenths),
+            pressure_pa: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::test_util::{block_on, I2cResponse, MockDelay, MockI2c, Recorder};
+
+    /// Measurement of the temperature and humidity words with their CRCs
+    fn measurement(temperature: u16, humidity: u16) -> Vec<u8> {
+        let (temperature, humidity) = (temperature.to_be_bytes(), humidity.to_be_bytes());
+        [
+            &temperature[..],
+            &[crc8(&temperature)],
+            &humidity[..],
+            &[crc8(&humidity)],
+        ]
+        .concat()
+    }
+
+    fn sensor(data: Vec<u8>) -> Sht3x<MockI2c, MockDelay> {
+        let responses = [I2cResponse::Read(vec![]), I2cResponse::Read(data)];
+        Sht3x::new(MockI2c::new(responses), Recorder::default().delay())
+    }
+
+    #[test]
+    fn read_converts_the_words() {
+        let mut sht3x = sensor(measurement(0x6666, 0x8000));
+
+        assert_eq!(
+            block_on(sht3x.read()),
+            Ok(EnvironmentReading {
+                temp_tenths_celsius: 250,
+                humidity_tenths: Some(500),
+                pressure_pa: None,
+            })
+        );
+    }
+
+    #[test]
+    fn read_rejects_a_bad_crc_of_either_word() {
+        for index in [2, 5] {
+            let mut data = measurement(0x6666, 0x8000);
+            data[index] ^= 0x01;
+            let mut sht3x = sensor(data);
+
+            assert_eq!(block_on(sht3x.read()), Err(SensorError::Checksum));
+        }
+    }
+}
@@ -0,0 +1,372 @@
+use heapless::Deque;
+
+use crate::sensor::EnvironmentReading;
+
+/// Number of buckets in the hour window, one bucket per minute
+const HOUR_BUCKETS: usize = 60;
+
+/// Length of a bucket in the hour window in seconds
+const HOUR_BUCKET_SECS: u64 = 60;
+
+/// Number of buckets in the day window, one bucket per 15 minutes
+const DAY_BUCKETS: usize = 96;
+
+/// Length of a bucket in the day window in seconds
+const DAY_BUCKET_SECS: u64 = 15 * 60;
+
+/// Summary of the values in a window
+///
+/// The values are in the same fixed point unit (tenths) as the values pushed
+/// into the window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Summary {
+    pub min: i16,
+    pub max: i16,
+    pub mean: i16,
+    pub std_dev: u16,
+    pub count: u32,
+}
+
+/// Aggregate of all values received during one bucket period
+#[derive(Clone, Copy, Debug)]
+struct Bucket {
+    start_secs: u64,
+    count: u32,
+    min: i16,
+    max: i16,
+    sum: i64,
+    sum_squares: i64,
+}
+
+impl Bucket {
+    fn new(start_secs: u64, value: i16) -> Self {
+        Self {
+            start_secs,
+            count: 1,
+            min: value,
+            max: value,
+            sum: value as i64,
+            sum_squares: value as i64 * value as i64,
+        }
+    }
+
+    fn add(&mut self, value: i16) {
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value as i64;
+        self.sum_squares += value as i64 * value as i64;
+    }
+}
+
+/// Rolling window of values kept as `N` fixed length buckets.
+///
+/// Instead of keeping every sample only an aggregate per bucket is stored, so a
+/// day of samples fits in a few kilobytes without allocating. The window covers
+/// the last `N` bucket periods, the oldest bucket is dropped as a new one starts.
+pub struct RollingWindow<const N: usize> {
+    bucket_secs: u64,
+    buckets: Deque<Bucket, N>,
+}
+
+impl<const N: usize> RollingWindow<N> {
+    /// Creates an empty window with buckets of `bucket_secs` seconds.
+    pub const fn new(bucket_secs: u64) -> Self {
+        Self {
+            bucket_secs,
+            buckets: Deque::new(),
+        }
+    }
+
+    /// Length of the window in seconds
+    pub fn window_secs(&self) -> u64 {
+        self.bucket_secs * N as u64
+    }
+
+    /// Add a value measured at `now_secs`.
+    pub fn push(&mut self, now_secs: u64, value: i16) {
+        self.expire(now_secs);
+
+        let start_secs = now_secs - now_secs % self.bucket_secs;
+        match self.buckets.back_mut() {
+            Some(bucket) if bucket.start_secs == start_secs => bucket.add(value),
+
+            _ => {
+                if self.buckets.is_full() {
+                    self.buckets.pop_front();
+                }
+                let _ = self.buckets.push_back(Bucket::new(start_secs, value));
+            }
+        }
+    }
+
+    /// Min, max, mean and standard deviation of the values still in the window at
+    /// `now_secs`, `None` if there are none.
+    pub fn summary(&self, now_secs: u64) -> Option<Summary> {
+        let mut count: u32 = 0;
+        let mut min = i16::MAX;
+        let mut max = i16::MIN;
+        let mut sum: i64 = 0;
+        let mut sum_squares: i64 = 0;
+
+        for bucket in self
+            .buckets
+            .iter()
+            .filter(|b| !self.is_expired(b, now_secs))
+        {
+            count += bucket.count;
+            min = min.min(bucket.min);
+            max = max.max(bucket.max);
+            sum += bucket.sum;
+            sum_squares += bucket.sum_squares;
+        }
+
+        if count == 0 {
+            return None;
+        }
+
+        // variance = (n * sum(x^2) - sum(x)^2) / n^2
+        let n = count as i64;
+        let variance = (n * sum_squares - sum * sum) / (n * n);
+
+        Some(Summary {
+            min,
+            max,
+            mean: div_round(sum, n) as i16,
+            std_dev: isqrt(variance.max(0) as u64) as u16,
+            count,
+        })
+    }
+
+    /// Drop the buckets that are no longer in the window
+    fn expire(&mut self, now_secs: u64) {
+        while let Some(bucket) = self.buckets.front() {
+            if !self.is_expired(bucket, now_secs) {
+                break;
+            }
+            self.buckets.pop_front();
+        }
+    }
+
+    fn is_expired(&self, bucket: &Bucket, now_secs: u64) -> bool {
+        bucket.start_secs + self.window_secs() <= now_secs
+    }
+}
+
+/// Windows the statistics are kept for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StatisticsWindow {
+    Hour,
+    Day,
+}
+
+impl StatisticsWindow {
+    /// Label shown in front of the statistics on the display
+    pub fn label(&self) -> &'static str {
+        match self {
+            StatisticsWindow::Hour => "1H",
+            StatisticsWindow::Day => "24H",
+        }
+    }
+}
+
+/// Temperature and humidity statistics over the last hour and the last 24 hours
+pub struct EnvironmentStatistics {
+    temperature_hour: RollingWindow<HOUR_BUCKETS>,
+    temperature_day: RollingWindow<DAY_BUCKETS>,
+    humidity_hour: RollingWindow<HOUR_BUCKETS>,
+    humidity_day: RollingWindow<DAY_BUCKETS>,
+}
+
+impl Default for EnvironmentStatistics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EnvironmentStatistics {
+    /// Creates empty statistics.
+    pub const fn new() -> Self {
+        Self {
+            temperature_hour: RollingWindow::new(HOUR_BUCKET_SECS),
+            temperature_day: RollingWindow::new(DAY_BUCKET_SECS),
+            humidity_hour: RollingWindow::new(HOUR_BUCKET_SECS),
+            humidity_day: RollingWindow::new(DAY_BUCKET_SECS),
+        }
+    }
+
+    /// Add a reading taken at `now_secs`.
+    pub fn push(&mut self, now_secs: u64, reading: &EnvironmentReading) {
+        self.temperature_hour
+            .push(now_secs, reading.temp_tenths_celsius);
+        self.temperature_day
+            .push(now_secs, reading.temp_tenths_celsius);
+
+        if let Some(humidity) = reading.humidity_tenths {
+            self.humidity_hour.push(now_secs, humidity as i16);
+            self.humidity_day.push(now_secs, humidity as i16);
+        }
+    }
+
+    /// Temperature statistics in tenths of a degree celsius
+    pub fn temperature(&self, window: StatisticsWindow, now_secs: u64) -> Option<Summary> {
+        match window {
+            StatisticsWindow::Hour => self.temperature_hour.summary(now_secs),
+            StatisticsWindow::Day => self.temperature_day.summary(now_secs),
+        }
+    }
+
+    /// Humidity statistics in tenths of a percent
+    pub fn humidity(&self, window: StatisticsWindow, now_secs: u64) -> Option<Summary> {
+        match window {
+            StatisticsWindow::Hour => self.humidity_hour.summary(now_secs),
+            StatisticsWindow::Day => self.humidity_day.summary(now_secs),
+        }
+    }
+}
+
+/// Integer division rounded to the nearest integer
+fn div_round(numerator: i64, denominator: i64) -> i64 {
+    if numerator >= 0 {
+        (numerator + denominator / 2) / denominator
+    } else {
+        (numerator - denominator / 2) / denominator
+    }
+}
+
+/// Integer square root rounded down
+fn isqrt(value: u64) -> u64 {
+    if value < 2 {
+        return value;
+    }
+
+    // Newton's method, starting above the root so it converges from above
+    let mut x = value;
+    let mut y = x.div_ceil(2);
+    while y < x {
+        x = y;
+        y = (x + value / x) / 2;
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn div_round_rounds_half_away_from_zero() {
+        for (numerator, denominator, expected) in [
+            (0, 3, 0),
+            (4, 3, 1),
+            (5, 3, 2),
+            (5, 2, 3),
+            (-1, 3, 0),
+            (-4, 3, -1),
+            (-5, 3, -2),
+            (-5, 2, -3),
+        ] {
+            assert_eq!(
+                div_round(numerator, denominator),
+                expected,
+                "{numerator} / {denominator}"
+            );
+        }
+    }
+
+    #[test]
+    fn isqrt_rounds_down() {
+        for (value, expected) in [
+            (0, 0),
+            (1, 1),
+            (2, 1),
+            (3, 1),
+            (4, 2),
+            (15, 3),
+            (16, 4),
+            (17, 4),
+            (u64::MAX, u32::MAX as u64),
+        ] {
+            assert_eq!(isqrt(value), expected, "{value}");
+        }
+
+        for root in 1..1000_u64 {
+            assert_eq!(isqrt(root * root), root);
+            assert_eq!(isqrt(root * root - 1), root - 1);
+        }
+    }
+
+    /// Push one value per bucket for all buckets, the first value is `first` and
+    /// the others `rest`, and check the window drops the first bucket when the
+    /// next one starts
+    fn check_rollover<const N: usize>(bucket_secs: u64, first: i16, rest: i16) {
+        let mut window = RollingWindow::<N>::new(bucket_secs);
+        for bucket in 0..N as u64 {
+            let value = if bucket == 0 { first } else { rest };
+            window.push(bucket * bucket_secs, value);
+        }
+
+        let end = N as u64 * bucket_secs;
+        let summary = window.summary(end - 1).unwrap();
+        assert_eq!((summary.min, summary.count), (first, N as u32));
+
+        window.push(end, rest);
+        let summary = window.summary(end).unwrap();
+        assert_eq!((summary.min, summary.max), (rest, rest));
+        assert_eq!(summary.count, N as u32);
+
+        // Without new values every bucket expires
+        assert_eq!(window.summary(2 * end), None);
+    }
+
+    #[test]
+    fn hour_window_rolls_over_every_minute() {
+        check_rollover::<HOUR_BUCKETS>(HOUR_BUCKET_SECS, 100, 200);
+    }
+
+    #[test]
+    fn day_window_rolls_over_every_15_minutes() {
+        check_rollover::<DAY_BUCKETS>(DAY_BUCKET_SECS, -100, -50);
+    }
+
+    #[test]
+    fn values_in_the_same_bucket_are_combined() {
+        let mut window = RollingWindow::<HOUR_BUCKETS>::new(HOUR_BUCKET_SECS);
+        for secs in (0..60).step_by(2) {
+            window.push(secs, 10);
+        }
+
+        assert_eq!(window.buckets.len(), 1);
+        assert_eq!(window.summary(59).unwrap().count, 30);
+    }
+
+    #[test]
+    fn summary_of_negative_temperatures() {
+        let mut window = RollingWindow::<HOUR_BUCKETS>::new(HOUR_BUCKET_SECS);
+        for (secs, value) in [(0, -200), (60, -150), (120, -100), (180, -50)] {
+            window.push(secs, value);
+        }
+
+        // Deviations from the mean are 75, 25, 25 and 75, the variance is 3125
+        assert_eq!(
+            window.summary(180),
+            Some(Summary {
+                min: -200,
+                max: -50,
+                mean: -125,
+                std_dev: 55,
+                count: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn mean_of_negative_temperatures_rounds_away_from_zero() {
+        let mut window = RollingWindow::<HOUR_BUCKETS>::new(HOUR_BUCKET_SECS);
+        window.push(0, -10);
+        window.push(1, -11);
+
+        let summary = window.summary(1).unwrap();
+        assert_eq!((summary.mean, summary.std_dev), (-11, 0));
+    }
+}
@@ -0,0 +1,359 @@
+//! Mocks of the display and sensor hardware recording what the drivers send,
+//! used by the tests of the library on the host.
+
+use core::cell::RefCell;
+use core::convert::Infallible;
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, Waker};
+use std::boxed::Box;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::vec;
+use std::vec::Vec;
+
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_hal::digital::{self, OutputPin};
+use embedded_hal::i2c::{self, ErrorKind, NoAcknowledgeSource};
+use embedded_hal::pwm::{self, SetDutyCycle};
+use embedded_hal::spi::{self, Operation};
+
+use crate::backlight::PinBacklight;
+use crate::double_buffer::{DoubleBuffer, Flush, FlushChannels, Flusher};
+use crate::ili9341_async::{Config, Ili9341};
+use crate::pixel_format::InterfaceColor;
+
+/// Run a future to completion, the mocks never wait so it is polled until it is ready
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+/// Double buffer with two buffers of `buffer_len` bytes, leaked to get the static lifetime
+pub fn double_buffer(buffer_len: usize) -> (DoubleBuffer, Flusher) {
+    let channels = Box::leak(Box::new(FlushChannels::new()));
+    let first = Box::leak(vec![0; buffer_len].into_boxed_slice());
+    let second = Box::leak(vec![0; buffer_len].into_boxed_slice());
+    channels.split(first, second)
+}
+
+/// Run a future drawing through a double buffer to completion, the buffers it submits
+/// are passed to `sent` in order and released as the flush task does.
+pub fn block_on_flushing<F: Future>(
+    future: F,
+    flusher: &mut Flusher,
+    mut sent: impl FnMut(&Flush),
+) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    let mut output = None;
+    loop {
+        if output.is_none() {
+            if let Poll::Ready(ready) = future.as_mut().poll(&mut cx) {
+                output = Some(ready);
+            }
+        }
+
+        let flush = match pin!(flusher.receive()).poll(&mut cx) {
+            Poll::Ready(flush) => flush,
+            // Everything submitted has been sent
+            Poll::Pending => match output {
+                Some(output) => return output,
+                None => continue,
+            },
+        };
+        sent(&flush);
+        flusher.release(flush);
+    }
+}
+
+/// Something the display driver did, in the order it happened
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    /// A command with the data sent after it until the next command
+    Command { command: u8, params: Vec<u8> },
+    /// A wait in milliseconds
+    Delay { ms: u32 },
+    /// The reset pin was set, `true` is high
+    Reset(bool),
+    /// The duty cycle of the PWM channel was set, out of [`MockPwm::MAX_DUTY_CYCLE`]
+    DutyCycle(u16),
+}
+
+#[derive(Default)]
+struct Recording {
+    /// Level of the data/command pin, data is sent while it is high
+    dc_high: bool,
+    events: Vec<Event>,
+}
+
+/// Records what the display driver sends to the SPI device, the pins and the delay.
+#[derive(Clone, Default)]
+pub struct Recorder(Rc<RefCell<Recording>>);
+
+impl Recorder {
+    /// Delay recording its waits
+    pub fn delay(&self) -> MockDelay {
+        MockDelay(self.clone())
+    }
+
+    /// PWM channel recording its duty cycles
+    pub fn pwm(&self) -> MockPwm {
+        MockPwm(self.clone())
+    }
+
+    /// Remove and return everything recorded so far
+    pub fn take(&self) -> Vec<Event> {
+        std::mem::take(&mut self.0.borrow_mut().events)
+    }
+
+    fn push(&self, event: Event) {
+        self.0.borrow_mut().events.push(event);
+    }
+
+    fn write(&self, data: &[u8]) {
+        let mut recording = self.0.borrow_mut();
+        if !recording.dc_high {
+            for command in data {
+                recording.events.push(Event::Command {
+                    command: *command,
+                    params: Vec::new(),
+                });
+            }
+            return;
+        }
+
+        match recording.events.last_mut() {
+            Some(Event::Command { params, .. }) => params.extend_from_slice(data),
+            _ => panic!("data sent without a command"),
+        }
+    }
+
+    fn transaction(&self, operations: &mut [Operation<'_, u8>]) {
+        for operation in operations {
+            match operation {
+                Operation::Write(data) => self.write(data),
+                Operation::Transfer(read, write) => {
+                    self.write(write);
+                    read.fill(0);
+                }
+                Operation::TransferInPlace(data) => {
+                    self.write(data);
+                    data.fill(0);
+                }
+                Operation::Read(data) => data.fill(0),
+                Operation::DelayNs(_) => {}
+            }
+        }
+    }
+}
+
+/// SPI device of the display, see [`Recorder`]
+pub struct MockSpi(Recorder);
+
+impl spi::ErrorType for MockSpi {
+    type Error = Infallible;
+}
+
+impl embedded_hal_async::spi::SpiDevice for MockSpi {
+    async fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_, u8>],
+    ) -> Result<(), Infallible> {
+        self.0.transaction(operations);
+        Ok(())
+    }
+}
+
+impl spi::SpiDevice for MockSpi {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Infallible> {
+        self.0.transaction(operations);
+        Ok(())
+    }
+}
+
+/// Data/command pin of the display, see [`Recorder`]
+pub struct MockDc(Recorder);
+
+impl digital::ErrorType for MockDc {
+    type Error = Infallible;
+}
+
+impl OutputPin for MockDc {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.0 .0.borrow_mut().dc_high = false;
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.0 .0.borrow_mut().dc_high = true;
+        Ok(())
+    }
+}
+
+/// Reset pin of the display, see [`Recorder`]
+pub struct MockReset(Recorder);
+
+impl digital::ErrorType for MockReset {
+    type Error = Infallible;
+}
+
+impl OutputPin for MockReset {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.0.push(Event::Reset(false));
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.0.push(Event::Reset(true));
+        Ok(())
+    }
+}
+
+/// Delay recording the waits in milliseconds, see [`Recorder`]
+#[derive(Clone)]
+pub struct MockDelay(Recorder);
+
+impl embedded_hal_async::delay::DelayNs for MockDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        self.0.push(Event::Delay {
+            ms: ns.div_ceil(1_000_000),
+        });
+    }
+
+    async fn delay_ms(&mut self, ms: u32) {
+        self.0.push(Event::Delay { ms });
+    }
+}
+
+/// PWM channel recording the duty cycles, see [`Recorder`]
+pub struct MockPwm(Recorder);
+
+impl MockPwm {
+    /// Duty cycle of a channel that is always on
+    pub const MAX_DUTY_CYCLE: u16 = 1000;
+}
+
+impl pwm::ErrorType for MockPwm {
+    type Error = Infallible;
+}
+
+impl SetDutyCycle for MockPwm {
+    fn max_duty_cycle(&self) -> u16 {
+        Self::MAX_DUTY_CYCLE
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Infallible> {
+        self.0.push(Event::DutyCycle(duty));
+        Ok(())
+    }
+}
+
+/// Output pin that does nothing, e.g. the backlight
+pub struct NoopPin;
+
+impl digital::ErrorType for NoopPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for NoopPin {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+pub type MockDisplay<C = Rgb565> = Ili9341<MockSpi, MockDc, MockReset, PinBacklight<NoopPin>, C>;
+
+/// Display driver recording what it sends, with a delay recording in the same recorder
+pub fn mock_display<C>(config: Config) -> (MockDisplay<C>, MockDelay, Recorder)
+where
+    C: InterfaceColor,
+{
+    let recorder = Recorder::default();
+    let display = Ili9341::with_color_format(
+        MockSpi(recorder.clone()),
+        MockDc(recorder.clone()),
+        MockReset(recorder.clone()),
+        PinBacklight::new(NoopPin),
+        config,
+    );
+
+    (display, recorder.delay(), recorder)
+}
+
+/// Response of [`MockI2c`] to a transaction
+pub enum I2cResponse {
+    /// The bytes read, the transaction succeeds
+    Read(Vec<u8>),
+    /// The device does not acknowledge its address
+    Nack,
+}
+
+/// I2C bus answering the transactions with scripted responses, a transaction
+/// without a response is not acknowledged.
+pub struct MockI2c {
+    responses: VecDeque<I2cResponse>,
+    /// Address of every transaction, in order
+    pub addresses: Vec<u8>,
+    /// Bytes written by every transaction, in order
+    pub writes: Vec<Vec<u8>>,
+}
+
+impl MockI2c {
+    /// Creates a bus answering the transactions with the responses in order
+    pub fn new(responses: impl IntoIterator<Item = I2cResponse>) -> Self {
+        Self {
+            responses: responses.into_iter().collect(),
+            addresses: Vec::new(),
+            writes: Vec::new(),
+        }
+    }
+}
+
+impl i2c::ErrorType for MockI2c {
+    type Error = ErrorKind;
+}
+
+impl embedded_hal_async::i2c::I2c for MockI2c {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [i2c::Operation<'_>],
+    ) -> Result<(), ErrorKind> {
+        self.addresses.push(address);
+
+        let mut written = Vec::new();
+        for operation in operations.iter() {
+            if let i2c::Operation::Write(data) = operation {
+                written.extend_from_slice(data);
+            }
+        }
+        self.writes.push(written);
+
+        let Some(I2cResponse::Read(bytes)) = self.responses.pop_front() else {
+            return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        };
+
+        let mut bytes = bytes.into_iter();
+        for operation in operations {
+            if let i2c::Operation::Read(buffer) = operation {
+                for byte in buffer.iter_mut() {
+                    *byte = bytes
+                        .next()
+                        .expect("not enough bytes scripted for the read");
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,183 @@
+use core::convert::Infallible;
+
+use embedded_graphics::{pixelcolor::Rgb565, prelude::*, primitives::Rectangle};
+
+use crate::compositor::{tiles, Tile};
+use crate::double_buffer::DoubleBuffer;
+
+/// Whole display as a [`DrawTarget`], drawn through pixel buffers smaller than the display.
+///
+/// A scene is drawn by a closure that is called once for every tile of the area
+/// being drawn. Each call only keeps the pixels inside the current tile, which is
+/// then sent to the display while the next tile is drawn in the other buffer. The
+/// closure sees the whole display, so any embedded-graphics scene renders
+/// correctly across the tile boundaries.
+///
+/// ```ignore
+/// TiledDisplay::new(&mut double_buffer, Size::new(320, 240))
+///     .draw(|target| {
+///         let _ = target.clear(Rgb565::BLACK);
+///     })
+///     .await;
+/// ```
+pub struct TiledDisplay<'a> {
+    /// Pixel buffers the tiles are drawn in and sent to the display.
+    double_buffer: &'a mut DoubleBuffer,
+    /// Tile currently being drawn, only set while the scene is called.
+    tile: Option<Tile<'static>>,
+    /// Size of the display in pixels.
+    size: Size,
+}
+
+impl<'a> TiledDisplay<'a> {
+    /// Creates a tiled display of `size` pixels drawing through `double_buffer`.
+    pub fn new(double_buffer: &'a mut DoubleBuffer, size: Size) -> Self {
+        Self {
+            double_buffer,
+            tile: None,
+            size,
+        }
+    }
+
+    /// Draw a scene on the whole display.
+    ///
+    /// # Parameters
+    ///
+    /// - `scene`: Draws the scene on the tiled display, called once per tile.
+    ///
+    /// # Panics
+    ///
+    /// If the buffers cannot hold a single pixel.
+    pub async fn draw<F>(&mut self, scene: F)
+    where
+        F: FnMut(&mut Self),
+    {
+        self.draw_area(self.bounding_box(), scene).await;
+    }
+
+    /// Draw a scene on an area of the display, the rest of the display is unchanged.
+    ///
+    /// # Parameters
+    ///
+    /// - `area`: Area of the display that is drawn, pixels outside of it are dropped.
+    /// - `scene`: Draws the scene on the tiled display, called once per tile.
+    ///
+    /// # Panics
+    ///
+    /// If the buffers cannot hold a single pixel.
+    pub async fn draw_area<F>(&mut self, area: Rectangle, mut scene: F)
+    where
+        F: FnMut(&mut Self),
+    {
+        let area = area.intersection(&self.bounding_box());
+        if area.is_zero_sized() {
+            return;
+        }
+
+        let max_pixels = self.double_buffer.buffer_pixels() as u32;
+        assert!(max_pixels > 0);
+
+        for tile_area in tiles(area, max_pixels) {
+            let buffer = self.double_buffer.acquire().await;
+            self.tile = Some(Tile::new(buffer, tile_area));
+            scene(self);
+
+            if let Some(tile) = self.tile.take() {
+                self.double_buffer
+                    .submit(tile.into_buffer(), tile_area)
+                    .await;
+            }
+        }
+    }
+}
+
+impl OriginDimensions for TiledDisplay<'_> {
+    fn size(&self) -> Size {
+        self.size
+    }
+}
+
+impl DrawTarget for TiledDisplay<'_> {
+    type Color = Rgb565;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        match self.tile.as_mut() {
+            Some(tile) => tile.draw_iter(pixels),
+            None => Ok(()),
+        }
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        match self.tile.as_mut() {
+            Some(tile) => tile.fill_solid(area, color),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use embedded_graphics::primitives::{Primitive, PrimitiveStyle};
+
+    use super::*;
+    use crate::test_util::{block_on_flushing, double_buffer};
+
+    /// Pixels of an 8x6 display after the flushes, `.` is black and `#` is red
+    fn draw_scene(area: Rectangle) -> (Vec<Rectangle>, [[char; 8]; 6]) {
+        // Each buffer holds 2 rows of the display
+        let (mut double_buffer, mut flusher) = double_buffer(8 * 2 * 2);
+        let mut areas = Vec::new();
+        let mut screen = [['?'; 8]; 6];
+
+        let draw = async {
+            TiledDisplay::new(&mut double_buffer, Size::new(8, 6))
+                .draw_area(area, |target| {
+                    let _ = target.clear(Rgb565::BLACK);
+                    let _ = Rectangle::new(Point::new(2, 1), Size::new(4, 4))
+                        .into_styled(PrimitiveStyle::with_fill(Rgb565::RED))
+                        .draw(target);
+                })
+                .await
+        };
+        block_on_flushing(draw, &mut flusher, |flush| {
+            let area = flush.area();
+            areas.push(area);
+            for (point, pixel) in area.points().zip(flush.data().chunks(2)) {
+                let red = u16::from_be_bytes([pixel[0], pixel[1]]) == 0xf800;
+                screen[point.y as usize][point.x as usize] = if red { '#' } else { '.' };
+            }
+        });
+
+        (areas, screen)
+    }
+
+    #[test]
+    fn scene_is_drawn_across_the_tiles() {
+        let display = Rectangle::new(Point::zero(), Size::new(8, 6));
+        let (areas, screen) = draw_scene(display);
+
+        let strip = |y| Rectangle::new(Point::new(0, y), Size::new(8, 2));
+        assert_eq!(areas, [strip(0), strip(2), strip(4)]);
+        assert_eq!(
+            screen.map(String::from_iter),
+            ["........", "..####..", "..####..", "..####..", "..####..", "........",]
+        );
+    }
+
+    #[test]
+    fn only_the_area_on_the_display_is_drawn() {
+        let (areas, screen) = draw_scene(Rectangle::new(Point::new(4, 3), Size::new(10, 10)));
+
+        assert_eq!(areas, [Rectangle::new(Point::new(4, 3), Size::new(4, 3))]);
+        assert_eq!(
+            screen.map(String::from_iter),
+            ["????????", "????????", "????????", "????##..", "????##..", "????....",]
+        );
+    }
+}
@@ -0,0 +1,476 @@
+use core::fmt::{self, Write};
+use heapless::String;
+
+use embedded_graphics::{
+    mono_font::{MonoFont, MonoTextStyle, MonoTextStyleBuilder},
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle, RoundedRectangle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+};
+
+/// Radius of the corners of a panel
+const PANEL_CORNER_RADIUS: u32 = 10;
+
+/// Distance between the left edge of a panel and its label
+const PANEL_LABEL_INSET: u32 = 16;
+
+/// Retained mode element of the screen.
+///
+/// A widget knows the area of the display it covers and whether it changed since
+/// it was last shown. Widgets are drawn by the [`Compositor`](crate::compositor::Compositor),
+/// which redraws the dirty areas in tiles.
+pub trait Widget {
+    /// Area of the display covered by the widget
+    fn bounding_box(&self) -> Rectangle;
+
+    /// Draw the widget in display coordinates, covering its whole bounding box
+    fn draw<T>(&self, target: &mut T)
+    where
+        T: DrawTarget<Color = Rgb565>;
+
+    /// Whether the widget changed since it was last shown
+    fn is_dirty(&self) -> bool;
+
+    /// Mark the widget as shown on the display
+    fn mark_clean(&mut self);
+
+    /// Area of the display that changed since the widget was last shown
+    fn dirty_area(&self) -> Option<Rectangle> {
+        self.is_dirty().then(|| self.bounding_box())
+    }
+}
+
+/// Size of `chars` characters of a monospaced font
+pub fn text_size(font: &MonoFont, chars: usize) -> Size {
+    let chars = chars as u32;
+    let spacing = chars.saturating_sub(1) * font.character_spacing;
+    Size::new(
+        chars * font.character_size.width + spacing,
+        font.character_size.height,
+    )
+}
+
+/// Draw text with its top left corner at `position`
+fn draw_text<T>(target: &mut T, text: &str, position: Point, style: MonoTextStyle<'_, Rgb565>)
+where
+    T: DrawTarget<Color = Rgb565>,
+{
+    let text_style = TextStyleBuilder::new()
+        .alignment(Alignment::Left)
+        .baseline(Baseline::Top)
+        .build();
+    let _ = Text::with_text_style(text, position, style, text_style).draw(target);
+}
+
+/// Fixed text, e.g. the name of a value.
+pub struct Label {
+    /// Top left corner of the text.
+    position: Point,
+    /// Text shown by the label.
+    text: &'static str,
+    /// Font and colors of the text, the background color erases what was below.
+    style: MonoTextStyle<'static, Rgb565>,
+    /// Whether the label changed since it was last shown.
+    dirty: bool,
+}
+
+impl Label {
+    /// Creates a label with its top left corner at `position`.
+    pub fn new(position: Point, text: &'static str, style: MonoTextStyle<'static, Rgb565>) -> Self {
+        Self {
+            position,
+            text,
+            style,
+            dirty: true,
+        }
+    }
+
+    /// Change the font or colors of the label
+    pub fn set_style(&mut self, style: MonoTextStyle<'static, Rgb565>) {
+        if style != self.style {
+            self.style = style;
+            self.dirty = true;
+        }
+    }
+}
+
+impl Widget for Label {
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(
+            self.position,
+            text_size(self.style.font, self.text.chars().count()),
+        )
+    }
+
+    fn draw<T>(&self, target: &mut T)
+    where
+        T: DrawTarget<Color = Rgb565>,
+    {
+        draw_text(target, self.text, self.position, self.style);
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    fn mark_clean(&mut self) {
+        self.dirty = false;
+    }
+}
+
+/// Text that changes, e.g. a reading.
+///
+/// The field is always `N` characters wide, so a shorter value erases a longer one.
+/// Values longer than `N` characters are cut off.
+pub struct ValueField<const N: usize> {
+    /// Top left corner of the field.
+    position: Point,
+    /// Value shown in the field.
+    value: String<N>,
+    /// Font and colors of the value, the background color fills the whole field.
+    style: MonoTextStyle<'static, Rgb565>,
+    /// Whether the value changed since it was last shown.
+    dirty: bool,
+}
+
+impl<const N: usize> ValueField<N> {
+    /// Creates an empty field with its top left corner at `position`.
+    pub fn new(position: Point, style: MonoTextStyle<'static, Rgb565>) -> Self {
+        Self {
+            position,
+            value: String::new(),
+            style,
+            dirty: true,
+        }
+    }
+
+    /// Change the value, the field is only redrawn if the value is different
+    pub fn set(&mut self, value: &str) {
+        self.set_fmt(format_args!("{value}"));
+    }
+
+    /// Change the value to formatted text, e.g. `field.set_fmt(format_args!("{t}F"))`
+    pub fn set_fmt(&mut self, args: fmt::Arguments) {
+        let mut value = String::<N>::new();
+        let _ = Truncate(&mut value).write_fmt(args);
+
+        if value != self.value {
+            self.value = value;
+            self.dirty = true;
+        }
+    }
+
+    /// Change the font or colors of the field
+    pub fn set_style(&mut self, style: MonoTextStyle<'static, Rgb565>) {
+        if style != self.style {
+            self.style = style;
+            self.dirty = true;
+        }
+    }
+}
+
+impl<const N: usize> Widget for ValueField<N> {
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(self.position, text_size(self.style.font, N))
+    }
+
+    fn draw<T>(&self, target: &mut T)
+    where
+        T: DrawTarget<Color = Rgb565>,
+    {
+        if let Some(background) = self.style.background_color {
+            let _ = target.fill_solid(&self.bounding_box(), background);
+        }
+        draw_text(target, &self.value, self.position, self.style);
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    fn mark_clean(&mut self) {
+        self.dirty = false;
+    }
+}
+
+/// Writer cutting the text off when the string is full, a heapless string drops
+/// the whole text that does not fit instead.
+struct Truncate<'a, const N: usize>(&'a mut String<N>);
+
+impl<const N: usize> Write for Truncate<'_, N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if self.0.push(c).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Text centered in a row, e.g. the screen title.
+///
+/// The whole row is redrawn when the text changes so a shorter text erases a longer one.
+pub struct Title<const N: usize> {
+    /// Row the text is centered in.
+    area: Rectangle,
+    /// Text shown in the row.
+    text: String<N>,
+    /// Font and colors of the text, the background color fills the whole row.
+    style: MonoTextStyle<'static, Rgb565>,
+    /// Whether the title changed since it was last shown.
+    dirty: bool,
+}
+
+impl<const N: usize> Title<N> {
+    /// Creates a title centered in `area`.
+    pub fn new(area: Rectangle, text: &str, style: MonoTextStyle<'static, Rgb565>) -> Self {
+        let mut title = Self {
+            area,
+            text: String::new(),
+            style,
+            dirty: true,
+        };
+        title.set(text, style);
+        title
+    }
+
+    /// Change the text and style, the title is only redrawn if either is different
+    pub fn set(&mut self, text: &str, style: MonoTextStyle<'static, Rgb565>) {
+        if text != self.text || style != self.style {
+            self.text.clear();
+            let _ = Truncate(&mut self.text).write_str(text);
+            self.style = style;
+            self.dirty = true;
+        }
+    }
+}
+
+impl<const N: usize> Widget for Title<N> {
+    fn bounding_box(&self) -> Rectangle {
+        self.area
+    }
+
+    fn draw<T>(&self, target: &mut T)
+    where
+        T: DrawTarget<Color = Rgb565>,
+    {
+        if let Some(background) = self.style.background_color {
+            let _ = target.fill_solid(&self.area, background);
+        }
+
+        let text_style = TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Middle)
+            .build();
+        let _ = Text::with_text_style(&self.text, self.area.center(), self.style, text_style)
+            .draw(target);
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    fn mark_clean(&mut self) {
+        self.dirty = false;
+    }
+}
+
+/// Rounded rectangle with a label on the left and an `N` character value on the right.
+///
+/// The label and value are vertically centered, their background is the panel
+/// color. Changing the color redraws the whole panel, changing the value only
+/// redraws the value.
+pub struct Panel<const N: usize> {
+    /// Area of the display covered by the panel.
+    area: Rectangle,
+    /// Fill color of the panel.
+    color: Rgb565,
+    /// Name of the value on the left of the panel.
+    label: Label,
+    /// Value on the right of the panel.
+    value: ValueField<N>,
+    /// Whether the panel itself changed since it was last shown.
+    dirty: bool,
+}
+
+impl<const N: usize> Panel<N> {
+    /// Creates a panel covering `area` with black text in `font`.
+    pub fn new(
+        area: Rectangle,
+        label: &'static str,
+        font: &'static MonoFont<'static>,
+        color: Rgb565,
+    ) -> Self {
+        let style = Self::char_style(font, color);
+        let text_top = (area.size.height.saturating_sub(font.character_size.height) / 2) as i32;
+        let value_width = text_size(font, N).width;
+        let value_left = area
+            .size
+            .width
+            .saturating_sub(PANEL_CORNER_RADIUS + value_width) as i32;
+
+        Self {
+            area,
+            color,
+            label: Label::new(
+                area.top_left + Point::new(PANEL_LABEL_INSET as i32, text_top),
+                label,
+                style,
+            ),
+            value: ValueField::new(area.top_left + Point::new(value_left, text_top), style),
+            dirty: true,
+        }
+    }
+
+    /// Change the value shown on the panel, only the value is redrawn
+    pub fn set_value(&mut self, args: fmt::Arguments) {
+        self.value.set_fmt(args);
+    }
+
+    /// Change the fill color, the whole panel is redrawn if the color is different
+    pub fn set_color(&mut self, color: Rgb565) {
+        if color != self.color {
+            let style = Self::char_style(self.label.style.font, color);
+            self.color = color;
+            self.label.set_style(style);
+            self.value.set_style(style);
+            self.dirty = true;
+        }
+    }
+
+    /// Style of the text on the panel
+    fn char_style(
+        font: &'static MonoFont<'static>,
+        color: Rgb565,
+    ) -> MonoTextStyle<'static, Rgb565> {
+        MonoTextStyleBuilder::new()
+            .font(font)
+            .text_color(Rgb565::BLACK)
+            // Need this so previous text is erased
+            .background_color(color)
+            .build()
+    }
+}
+
+impl<const N: usize> Widget for Panel<N> {
+    fn bounding_box(&self) -> Rectangle {
+        self.area
+    }
+
+    fn draw<T>(&self, target: &mut T)
+    where
+        T: DrawTarget<Color = Rgb565>,
+    {
+        let _ = RoundedRectangle::with_equal_corners(
+            self.area,
+            Size::new(PANEL_CORNER_RADIUS, PANEL_CORNER_RADIUS),
+        )
+        .into_styled(PrimitiveStyle::with_fill(self.color))
+        .draw(target);
+
+        self.label.draw(target);
+        self.value.draw(target);
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty || self.label.is_dirty() || self.value.is_dirty()
+    }
+
+    fn mark_clean(&mut self) {
+        self.dirty = false;
+        self.label.mark_clean();
+        self.value.mark_clean();
+    }
+
+    fn dirty_area(&self) -> Option<Rectangle> {
+        if self.dirty || self.label.is_dirty() {
+            Some(self.area)
+        } else {
+            self.value.dirty_area()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::mono_font::ascii::FONT_10X20;
+
+    use super::*;
+
+    fn style() -> MonoTextStyle<'static, Rgb565> {
+        MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE)
+    }
+
+    #[test]
+    fn value_field_covers_n_characters() {
+        let mut field = ValueField::<5>::new(Point::new(3, 4), style());
+        field.set("1");
+
+        assert_eq!(
+            field.bounding_box(),
+            Rectangle::new(Point::new(3, 4), Size::new(50, 20))
+        );
+    }
+
+    #[test]
+    fn value_field_is_only_dirty_when_the_value_changes() {
+        let mut field = ValueField::<5>::new(Point::new(3, 4), style());
+        assert_eq!(field.dirty_area(), Some(field.bounding_box()));
+
+        field.set_fmt(format_args!("{}F", 72));
+        field.mark_clean();
+        field.set("72F");
+        assert_eq!(field.dirty_area(), None);
+
+        field.set("73F");
+        assert_eq!(field.dirty_area(), Some(field.bounding_box()));
+
+        // Values longer than the field are cut off, so a longer value is the same
+        field.mark_clean();
+        field.set("73F and more");
+        field.mark_clean();
+        field.set("73F a");
+        assert_eq!(field.dirty_area(), None);
+    }
+
+    #[test]
+    fn title_is_only_dirty_when_the_text_or_style_changes() {
+        let area = Rectangle::new(Point::zero(), Size::new(320, 30));
+        let mut title = Title::<16>::new(area, "Title", style());
+        assert_eq!(title.dirty_area(), Some(area));
+        title.mark_clean();
+
+        title.set("Title", style());
+        assert_eq!(title.dirty_area(), None);
+
+        title.set("Title", MonoTextStyle::new(&FONT_10X20, Rgb565::RED));
+        assert_eq!(title.dirty_area(), Some(area));
+    }
+
+    #[test]
+    fn panel_redraws_only_the_value_when_the_value_changes() {
+        let area = Rectangle::new(Point::new(0, 40), Size::new(200, 50));
+        let mut panel = Panel::<4>::new(area, "Temp", &FONT_10X20, Rgb565::GREEN);
+        assert_eq!(panel.bounding_box(), area);
+        assert_eq!(panel.dirty_area(), Some(area));
+        panel.mark_clean();
+
+        // The value is right aligned inside the corner radius and centered vertically
+        panel.set_value(format_args!("{}", 21));
+        assert_eq!(
+            panel.dirty_area(),
+            Some(Rectangle::new(Point::new(150, 55), Size::new(40, 20)))
+        );
+        panel.mark_clean();
+
+        panel.set_color(Rgb565::GREEN);
+        assert_eq!(panel.dirty_area(), None);
+
+        panel.set_color(Rgb565::YELLOW);
+        assert_eq!(panel.dirty_area(), Some(area));
+        panel.mark_clean();
+        assert!(!panel.is_dirty());
+    }
+}